@@ -0,0 +1,284 @@
+use near_event_listener::EventLog;
+use near_jsonrpc_client::methods;
+use near_jsonrpc_primitives::types::blocks::RpcBlockError;
+use near_jsonrpc_primitives::types::chunks::ChunkReference;
+use near_jsonrpc_primitives::types::transactions::TransactionInfo;
+use near_primitives::types::{BlockId, BlockReference};
+use near_primitives::views::{ActionView, TxExecutionStatus};
+use near_sdk::AccountId;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::sleep;
+
+use crate::checkpoint::latest_final_height;
+use crate::error::ClientError;
+use crate::processors::parse_event;
+use crate::rpc_pool::RpcPool;
+use crate::tx_sender::final_outcome;
+
+/// Walks final blocks in order and collects the NEP-297 events logged by calls to
+/// `method_name` on `account_id`.
+///
+/// Blocks are handed to the caller one at a time with their height, so the checkpoint can
+/// record exactly how far the listener got.
+pub struct BlockListener {
+    client: Arc<RpcPool>,
+    account_id: AccountId,
+    method_name: String,
+    poll_interval: Duration,
+}
+
+impl BlockListener {
+    pub fn new(
+        client: Arc<RpcPool>,
+        account_id: AccountId,
+        method_name: String,
+        poll_interval: Duration,
+    ) -> Self {
+        Self {
+            client,
+            account_id,
+            method_name,
+            poll_interval,
+        }
+    }
+
+    /// Calls `on_block` for every final block from `start_block` on, never returning. A block
+    /// that fails to load is retried, never skipped.
+    pub async fn run<F>(&self, start_block: u64, mut on_block: F)
+    where
+        F: FnMut(u64, Vec<EventLog>),
+    {
+        let mut height = start_block;
+
+        loop {
+            let final_height = match latest_final_height(&self.client).await {
+                Ok(final_height) => final_height,
+                Err(e) => {
+                    eprintln!("Failed to fetch final block: {}", e);
+                    sleep(self.poll_interval).await;
+                    continue;
+                }
+            };
+
+            while height <= final_height {
+                match self.block_events(height).await {
+                    Ok(events) => {
+                        on_block(height, events);
+                        height += 1;
+                    }
+                    Err(e) => {
+                        eprintln!("Failed to read block {}: {}", height, e);
+                        sleep(self.poll_interval).await;
+                    }
+                }
+            }
+
+            sleep(self.poll_interval).await;
+        }
+    }
+
+    async fn block_events(&self, height: u64) -> Result<Vec<EventLog>, ClientError> {
+        let block = match self
+            .client
+            .call(methods::block::RpcBlockRequest {
+                block_reference: BlockReference::BlockId(BlockId::Height(height)),
+            })
+            .await
+        {
+            Ok(block) => block,
+            // Heights below the final head without a block were skipped by the chain
+            Err(err)
+                if matches!(
+                    err.handler_error(),
+                    Some(RpcBlockError::UnknownBlock { .. })
+                ) =>
+            {
+                return Ok(Vec::new())
+            }
+            Err(err) => return Err(err.into()),
+        };
+
+        let mut events = Vec::new();
+        for chunk_header in &block.chunks {
+            // A chunk missing from this block is repeated from an earlier one
+            if chunk_header.height_included != height {
+                continue;
+            }
+
+            let chunk = self
+                .client
+                .call(methods::chunk::RpcChunkRequest {
+                    chunk_reference: ChunkReference::ChunkHash {
+                        chunk_id: chunk_header.chunk_hash,
+                    },
+                })
+                .await?;
+
+            for transaction in &chunk.transactions {
+                let calls_method = transaction.actions.iter().any(|action| match action {
+                    ActionView::FunctionCall { method_name, .. } => {
+                        *method_name == self.method_name
+                    }
+                    _ => false,
+                });
+                if transaction.receiver_id != self.account_id || !calls_method {
+                    continue;
+                }
+
+                let response = self
+                    .client
+                    .call(methods::tx::RpcTransactionStatusRequest {
+                        transaction_info: TransactionInfo::TransactionId {
+                            tx_hash: transaction.hash,
+                            sender_account_id: transaction.signer_id.clone(),
+                        },
+                        wait_until: TxExecutionStatus::Final,
+                    })
+                    .await?;
+
+                if let Some(outcome) = final_outcome(&response) {
+                    events.extend(
+                        std::iter::once(&outcome.transaction_outcome)
+                            .chain(&outcome.receipts_outcome)
+                            .flat_map(|receipt| &receipt.outcome.logs)
+                            .filter_map(|log| parse_event(log)),
+                    );
+                }
+            }
+        }
+
+        Ok(events)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+    use std::sync::Mutex;
+
+    use crate::test_server::{
+        block_result, chunk_header, chunk_result, hash_of, outcome_result, rpc_error, rpc_result,
+        transaction_view, StandIn,
+    };
+
+    const LISTENED: &str = "earthmind.test.near";
+    const METHOD: &str = "request_governance_decision";
+    const EVENT: &str = r#"EVENT_JSON:{"standard":"emip001","version":"1.0.0","event":"request_governance_decision","data":[{"request_id":"r1","start_time":1}]}"#;
+
+    /// Node on which height 10 was skipped and block 11 repeats the chunk of another shard from
+    /// block 10. Its own chunk holds one matching transaction and two that only half match.
+    /// Records every request as its method and params.
+    async fn node() -> (StandIn, Arc<Mutex<Vec<(String, Value)>>>) {
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let seen = requests.clone();
+
+        let server = StandIn::start(move |_, request| {
+            let method = request["method"].as_str().unwrap_or_default().to_string();
+            let params = request["params"].clone();
+            seen.lock().unwrap().push((method.clone(), params.clone()));
+
+            let result = match method.as_str() {
+                "block" if params["block_id"] == 11 => block_result(
+                    11,
+                    vec![chunk_header("chunk 11", 11), chunk_header("chunk 10", 10)],
+                ),
+                "block" => {
+                    return (
+                        200,
+                        rpc_error(
+                            request,
+                            json!({
+                                "name": "HANDLER_ERROR",
+                                "cause": {
+                                    "name": "UNKNOWN_BLOCK",
+                                    "info": { "error_message": "DB Not Found Error" }
+                                },
+                                "code": -32000,
+                                "message": "Server error",
+                                "data": "DB Not Found Error"
+                            }),
+                        ),
+                    )
+                }
+                "chunk" if params["chunk_id"] == hash_of("chunk 11") => chunk_result(
+                    chunk_header("chunk 11", 11),
+                    vec![
+                        transaction_view("match", "alice.test.near", LISTENED, METHOD),
+                        transaction_view("other method", "alice.test.near", LISTENED, "commit"),
+                        transaction_view("other account", "alice.test.near", "x.test.near", METHOD),
+                    ],
+                ),
+                "tx" if params["tx_hash"] == hash_of("match") => {
+                    let transaction =
+                        transaction_view("match", "alice.test.near", LISTENED, METHOD);
+                    let mut result = outcome_result(transaction, &[EVENT, "not an event"]);
+                    result["final_execution_status"] = json!("FINAL");
+                    result
+                }
+                _ => return (500, Value::Null),
+            };
+            (200, rpc_result(request, result))
+        })
+        .await;
+
+        (server, requests)
+    }
+
+    fn listener(server: &StandIn) -> BlockListener {
+        BlockListener::new(
+            Arc::new(RpcPool::new(&[server.url.clone()], Duration::from_secs(5))),
+            LISTENED.parse().unwrap(),
+            METHOD.to_string(),
+            Duration::from_millis(10),
+        )
+    }
+
+    fn requested(requests: &Mutex<Vec<(String, Value)>>, method: &str, key: &str) -> Vec<Value> {
+        requests
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(name, _)| name == method)
+            .map(|(_, params)| params[key].clone())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn skipped_height_has_no_events() {
+        let (server, requests) = node().await;
+
+        let events = listener(&server).block_events(10).await.unwrap();
+
+        assert!(events.is_empty());
+        assert_eq!(requests.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn repeated_chunk_is_not_read_again() {
+        let (server, requests) = node().await;
+
+        listener(&server).block_events(11).await.unwrap();
+
+        assert_eq!(
+            requested(&requests, "chunk", "chunk_id"),
+            [json!(hash_of("chunk 11"))]
+        );
+    }
+
+    #[tokio::test]
+    async fn events_come_only_from_matching_transactions() {
+        let (server, requests) = node().await;
+
+        let events = listener(&server).block_events(11).await.unwrap();
+
+        assert_eq!(
+            requested(&requests, "tx", "tx_hash"),
+            [json!(hash_of("match"))]
+        );
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].event, METHOD);
+        assert_eq!(events[0].data[0]["request_id"], "r1");
+    }
+}
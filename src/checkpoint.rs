@@ -0,0 +1,137 @@
+use near_jsonrpc_client::methods;
+use near_primitives::types::{BlockReference, Finality};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::constants::CHECKPOINT_FILE;
+use crate::error::ClientError;
+use crate::rpc_pool::RpcPool;
+
+/// Last fully processed block height, persisted in the data directory.
+///
+/// A block is processed once the listener has scanned it and every processor task spawned for
+/// its events has finished. Tasks outlive later blocks, so the checkpoint stops just below the
+/// oldest block that still has one running; a restart then replays from there.
+pub struct Checkpoint {
+    path: PathBuf,
+    state: Mutex<CheckpointState>,
+    saved: AtomicU64,
+}
+
+#[derive(Default)]
+struct CheckpointState {
+    /// Highest block the listener has finished scanning
+    listened: Option<u64>,
+    /// Running processor tasks per block
+    in_flight: BTreeMap<u64, usize>,
+}
+
+impl Checkpoint {
+    pub fn new(data_dir: &Path) -> Self {
+        Self {
+            path: data_dir.join(CHECKPOINT_FILE),
+            state: Mutex::new(CheckpointState::default()),
+            saved: AtomicU64::new(0),
+        }
+    }
+
+    pub fn load(&self) -> Result<Option<u64>, Box<dyn std::error::Error + Send + Sync>> {
+        match fs::read_to_string(&self.path) {
+            Ok(contents) => {
+                let height = contents.trim().parse::<u64>().map_err(|err| {
+                    format!("Corrupted checkpoint {}: {}", self.path.display(), err)
+                })?;
+                self.saved.store(height, Ordering::SeqCst);
+                Ok(Some(height))
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Writes to a temporary file and renames it over the checkpoint, so a crash leaves either
+    /// the old or the new height on disk.
+    pub fn save(&self, height: u64) -> std::io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let tmp_path = self.path.with_extension("tmp");
+        let mut file = fs::File::create(&tmp_path)?;
+        file.write_all(height.to_string().as_bytes())?;
+        file.sync_all()?;
+        fs::rename(&tmp_path, &self.path)?;
+
+        self.saved.store(height, Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// A processor task for an event of block `height` started.
+    pub fn begin_event(&self, height: u64) {
+        *self
+            .state
+            .lock()
+            .unwrap()
+            .in_flight
+            .entry(height)
+            .or_default() += 1;
+    }
+
+    pub fn finish_event(&self, height: u64) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(count) = state.in_flight.get_mut(&height) {
+            *count -= 1;
+            if *count == 0 {
+                state.in_flight.remove(&height);
+            }
+        }
+    }
+
+    /// The listener scanned block `height` and started the tasks for all of its events.
+    pub fn finish_block(&self, height: u64) {
+        self.state.lock().unwrap().listened = Some(height);
+    }
+
+    /// Highest block that, with every block before it, is fully processed.
+    fn processed_height(&self) -> Option<u64> {
+        let state = self.state.lock().unwrap();
+        let listened = state.listened?;
+        match state.in_flight.keys().next() {
+            Some(&oldest) => oldest.checked_sub(1).map(|height| height.min(listened)),
+            None => Some(listened),
+        }
+    }
+
+    /// Saves the processed height every `interval` when it has advanced.
+    pub async fn run(self: Arc<Self>, interval: Duration) {
+        loop {
+            tokio::time::sleep(interval).await;
+
+            let Some(height) = self.processed_height() else {
+                continue;
+            };
+            if height <= self.saved.load(Ordering::SeqCst) {
+                continue;
+            }
+
+            if let Err(e) = self.save(height) {
+                eprintln!("Failed to save checkpoint: {}", e);
+            }
+        }
+    }
+}
+
+pub async fn latest_final_height(client: &RpcPool) -> Result<u64, ClientError> {
+    let block = client
+        .call(methods::block::RpcBlockRequest {
+            block_reference: BlockReference::Finality(Finality::Final),
+        })
+        .await?;
+
+    Ok(block.header.height)
+}
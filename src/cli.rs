@@ -1,33 +1,318 @@
-use clap::{Parser, ValueEnum};
-use near_crypto::SecretKey;
+use clap::{Parser, Subcommand, ValueEnum};
+use near_crypto::{PublicKey, SecretKey};
+use near_primitives::views::TxExecutionStatus;
 use near_sdk::AccountId;
+use serde::Deserialize;
+use std::path::PathBuf;
+
+use crate::config::{Config, ConfigError};
+use crate::journal::TxStatus;
+use crate::signer::RemoteEndpoint;
 
 #[derive(Parser)]
 #[command(name = "earthmind_client_listener")]
 #[command(about = "A Near block listener with different modes", long_about = None)]
 pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Role to run the listener as; required when no subcommand is given
     #[arg(long, value_enum)]
-    pub mode: Modes,
+    pub mode: Option<Modes>,
+
+    #[arg(long)]
+    pub account_id: Option<AccountId>,
+
+    /// Run a role as `<mode>=<account_id>`; repeat to run several roles in one process
+    #[arg(long = "role", value_name = "MODE=ACCOUNT_ID", value_parser = parse_role)]
+    pub roles: Vec<(Modes, AccountId)>,
+
+    /// NEAR CLI style JSON key file with `account_id`, `public_key` and `private_key`
+    #[arg(long, env = "EARTHMIND_KEY_FILE")]
+    pub key_file: Option<PathBuf>,
+
+    /// Extra function-call access key files for the signing accounts, used to send several
+    /// transactions in parallel; repeat or comma-separate
+    #[arg(
+        long = "access-key-file",
+        env = "EARTHMIND_ACCESS_KEY_FILES",
+        value_delimiter = ','
+    )]
+    pub access_key_files: Vec<PathBuf>,
 
+    /// Directory holding `<network>/<account>.json` credentials (default: ~/.near-credentials)
+    #[arg(long, env = "EARTHMIND_CREDENTIALS_DIR")]
+    pub credentials_dir: Option<PathBuf>,
+
+    /// Plain text secret key, only used when no other key source is found
     #[arg(long)]
-    pub account_id: AccountId,
+    pub private_key: Option<SecretKey>,
+
+    /// Directory of the encrypted keystore (default: ~/.earthmind/keystore)
+    #[arg(long, env = "EARTHMIND_KEYSTORE_DIR", global = true)]
+    pub keystore_dir: Option<PathBuf>,
+
+    /// File whose first line is the keystore passphrase; prompts when not given
+    #[arg(long, env = "EARTHMIND_PASSPHRASE_FILE", global = true)]
+    pub passphrase_file: Option<PathBuf>,
+
+    /// TOML file with the client configuration
+    #[arg(long, env = "EARTHMIND_CONFIG")]
+    pub config: Option<PathBuf>,
+
+    #[arg(long, value_enum, env = "EARTHMIND_NETWORK")]
+    pub network: Option<Networks>,
+
+    #[arg(long, env = "EARTHMIND_RPC_URL")]
+    pub rpc_url: Option<String>,
+
+    /// Extra RPC endpoints to fail over to; repeat or comma-separate
+    #[arg(
+        long = "rpc-fallback-url",
+        env = "EARTHMIND_RPC_FALLBACK_URLS",
+        value_delimiter = ','
+    )]
+    pub rpc_fallback_urls: Vec<String>,
+
+    #[arg(long, env = "EARTHMIND_HEALTH_CHECK_INTERVAL_SECS")]
+    pub health_check_interval_secs: Option<u64>,
+
+    #[arg(long, env = "EARTHMIND_ACCOUNT_TO_LISTEN")]
+    pub account_to_listen: Option<String>,
+
+    #[arg(long, env = "EARTHMIND_FUNCTION_TO_LISTEN")]
+    pub function_to_listen: Option<String>,
+
+    #[arg(long, env = "EARTHMIND_PROTOCOL_CONTRACT")]
+    pub protocol_contract: Option<String>,
+
+    #[arg(long, env = "EARTHMIND_TIMEOUT_SECS")]
+    pub timeout_secs: Option<u64>,
+
+    #[arg(long, env = "EARTHMIND_POLL_INTERVAL_SECS")]
+    pub poll_interval_secs: Option<u64>,
+
+    #[arg(long, env = "EARTHMIND_STAGE_ATTEMPTS")]
+    pub stage_attempts: Option<u32>,
+
+    #[arg(long, env = "EARTHMIND_RETRY_MAX_ATTEMPTS")]
+    pub retry_max_attempts: Option<u32>,
+
+    /// First delay between transaction retries; doubles up to --retry-max-delay-ms
+    #[arg(long, env = "EARTHMIND_RETRY_BASE_DELAY_MS")]
+    pub retry_base_delay_ms: Option<u64>,
+
+    #[arg(long, env = "EARTHMIND_RETRY_MAX_DELAY_MS")]
+    pub retry_max_delay_ms: Option<u64>,
+
+    /// How long a transaction is retried and its status polled before giving up
+    #[arg(long, env = "EARTHMIND_RETRY_DEADLINE_SECS")]
+    pub retry_deadline_secs: Option<u64>,
+
+    /// How far a transaction must get before the send returns (default: final)
+    #[arg(long, value_enum, env = "EARTHMIND_WAIT_UNTIL")]
+    pub wait_until: Option<WaitLevel>,
+
+    /// Send commit and reveal calls as NEP-366 meta transactions through this relayer, which
+    /// pays their gas
+    #[arg(
+        long,
+        env = "EARTHMIND_RELAYER_URL",
+        conflicts_with = "export_unsigned"
+    )]
+    pub relayer_url: Option<String>,
+
+    /// Directory for the block checkpoint and other local state
+    #[arg(long, env = "EARTHMIND_DATA_DIR")]
+    pub data_dir: Option<PathBuf>,
 
+    /// How often the processed block height is saved
+    #[arg(long, env = "EARTHMIND_CHECKPOINT_INTERVAL_SECS")]
+    pub checkpoint_interval_secs: Option<u64>,
+
+    /// Start listening at this block height instead of the saved checkpoint
+    #[arg(long, conflicts_with = "from_latest")]
+    pub from_block: Option<u64>,
+
+    /// Start listening at the latest final block instead of the saved checkpoint
     #[arg(long)]
-    pub private_key: SecretKey,
+    pub from_latest: bool,
+
+    /// Build and sign transactions but print them instead of broadcasting
+    #[arg(long, global = true, env = "EARTHMIND_DRY_RUN")]
+    pub dry_run: bool,
+
+    /// Write transactions to this directory unsigned, for `sign` on an offline machine, instead
+    /// of signing and broadcasting them
+    #[arg(long, global = true, value_name = "DIR", conflicts_with = "dry_run")]
+    pub export_unsigned: Option<PathBuf>,
+
+    /// Sign with a key held by a separate process, at `unix:<path>` or an http(s) URL, instead
+    /// of a local key
+    #[arg(
+        long,
+        global = true,
+        env = "EARTHMIND_REMOTE_SIGNER",
+        value_name = "unix:<PATH>|URL"
+    )]
+    pub remote_signer: Option<RemoteEndpoint>,
+
+    /// Public key of the offline signing key, so --export-unsigned needs no private key
+    #[arg(long, global = true, requires = "export_unsigned")]
+    pub public_key: Option<PublicKey>,
+}
+
+impl Cli {
+    /// Loads the config file (if any) and applies flags and environment variables on top of it.
+    pub fn load_config(&self) -> Result<Config, ConfigError> {
+        Config::resolve(self)
+    }
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Manage the encrypted keystore
+    #[command(subcommand)]
+    Keys(KeysCommand),
+    /// Print the current stage of the request started at `start_time`
+    Stage { start_time: u64 },
+    /// Commit the miner answer for a request
+    CommitMiner { request_id: String },
+    /// Reveal the miner answer for a request
+    RevealMiner { request_id: String },
+    /// Commit the validator answer for a request
+    CommitValidator { request_id: String },
+    /// Reveal the validator answer for a request
+    RevealValidator { request_id: String },
+    /// Ask the protocol for the top ten voters of a request
+    TopTen { request_id: String },
+    /// Account maintenance transactions signed by `--account-id`
+    #[command(subcommand)]
+    Account(AccountCommand),
+    /// Sign a transaction exported with --export-unsigned; needs no network access
+    Sign {
+        input: PathBuf,
+
+        /// Where to write the signed transaction (default: `<hash>.signed.json` next to it)
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+    /// Submit a transaction produced by `sign`
+    Broadcast { input: PathBuf },
+    /// List journaled transactions, newest last, with the latest known status of each
+    History {
+        #[arg(long)]
+        request_id: Option<String>,
+
+        /// miner, validator, aggregator or account
+        #[arg(long)]
+        role: Option<String>,
+
+        #[arg(long, value_enum)]
+        status: Option<TxStatus>,
+
+        /// Show at most this many transactions
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum KeysCommand {
+    /// Encrypt a secret key into the keystore
+    Import {
+        account_id: AccountId,
+
+        /// Read the key from a NEAR CLI style JSON key file instead of prompting
+        #[arg(long)]
+        from_file: Option<PathBuf>,
+    },
+    /// List the accounts stored in the keystore
+    List,
+    /// Print the public key stored for an account
+    ExportPublic { account_id: AccountId },
+}
+
+#[derive(Subcommand)]
+pub enum AccountCommand {
+    /// Send `amount` yoctoNEAR to `receiver_id`
+    Transfer {
+        receiver_id: AccountId,
+        amount: u128,
+    },
+    /// Add a full access key, or with --function-call a key limited to the protocol contract
+    AddKey {
+        public_key: PublicKey,
+
+        #[arg(long)]
+        function_call: bool,
+
+        /// Gas allowance of a function-call key in yoctoNEAR (default: unlimited)
+        #[arg(long, requires = "function_call")]
+        allowance: Option<u128>,
 
-    #[arg(long, default_value = "testnet")]
-    pub network: Networks,
+        /// Methods a function-call key may call (default: all); repeat or comma-separate
+        #[arg(
+            long = "method-name",
+            requires = "function_call",
+            value_delimiter = ','
+        )]
+        method_names: Vec<String>,
+    },
+    /// Remove an access key from the account
+    DeleteKey { public_key: PublicKey },
+    /// Stake `amount` yoctoNEAR with `public_key` as the validator key
+    Stake { amount: u128, public_key: PublicKey },
 }
 
-#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+fn parse_role(value: &str) -> Result<(Modes, AccountId), String> {
+    let (mode, account_id) = value
+        .split_once('=')
+        .ok_or_else(|| format!("expected MODE=ACCOUNT_ID, got `{}`", value))?;
+    let mode = Modes::from_str(mode, true)?;
+    let account_id = account_id.parse().map_err(|err| format!("{}", err))?;
+
+    Ok((mode, account_id))
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Modes {
     Miner,
     Validator,
     Aggregator,
 }
 
-#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Networks {
     Testnet,
+    /// Protocol contract preset only; the listened account must be supplied
     Mainnet,
+    /// Local sandbox node with the default sandbox account names
+    Localnet,
+    /// RPC URL, protocol contract and listened account must all be supplied
+    Custom,
+}
+
+/// Execution status a transaction send waits for. Anything short of `Final` is confirmed in
+/// the background afterwards.
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum WaitLevel {
+    /// Included in a block, not yet executed
+    Included,
+    /// Executed, but the blocks may not be final yet
+    ExecutedOptimistic,
+    Final,
+}
+
+impl From<WaitLevel> for TxExecutionStatus {
+    fn from(level: WaitLevel) -> Self {
+        match level {
+            WaitLevel::Included => TxExecutionStatus::Included,
+            WaitLevel::ExecutedOptimistic => TxExecutionStatus::ExecutedOptimistic,
+            WaitLevel::Final => TxExecutionStatus::Final,
+        }
+    }
 }
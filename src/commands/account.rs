@@ -0,0 +1,62 @@
+use near_sdk::AccountId;
+
+use crate::cli::AccountCommand;
+use crate::config::Config;
+use crate::journal::TxContext;
+use crate::key_pool::AccessKeyPool;
+use crate::tx_builder::TxBuilder;
+use crate::tx_sender::TxSender;
+
+pub async fn run_account_command(
+    command: &AccountCommand,
+    key_pool: &AccessKeyPool,
+    tx_sender: &TxSender,
+    account_id: &AccountId,
+    config: &Config,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let builder = match command {
+        AccountCommand::Transfer {
+            receiver_id,
+            amount,
+        } => TxBuilder::new(receiver_id.clone()).transfer(*amount),
+        AccountCommand::AddKey {
+            public_key,
+            function_call: true,
+            allowance,
+            method_names,
+        } => TxBuilder::new(account_id.clone()).add_function_call_key(
+            public_key.clone(),
+            *allowance,
+            &config.protocol_contract,
+            method_names.clone(),
+        ),
+        AccountCommand::AddKey { public_key, .. } => {
+            TxBuilder::new(account_id.clone()).add_full_access_key(public_key.clone())
+        }
+        AccountCommand::DeleteKey { public_key } => {
+            TxBuilder::new(account_id.clone()).delete_key(public_key.clone())
+        }
+        AccountCommand::Stake { amount, public_key } => {
+            TxBuilder::new(account_id.clone()).stake(*amount, public_key.clone())
+        }
+    };
+
+    let key = key_pool.acquire().await;
+    let (nonce, block_hash) = key.nonce_manager().get_nonce_and_tx_hash().await?;
+    let tx = builder.build(key.signer(), nonce, block_hash);
+
+    let tx_response = tx_sender
+        .sign_and_send(
+            tx,
+            config.wait_until.into(),
+            key.signer(),
+            key.nonce_manager(),
+            &TxContext::role("account"),
+        )
+        .await?;
+    if let Some(outcome) = &tx_response.final_execution_outcome {
+        println!("Transaction outcome: {:?}", outcome);
+    }
+
+    Ok(())
+}
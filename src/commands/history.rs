@@ -0,0 +1,58 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::journal::{self, JournalRecord, TxStatus};
+
+/// Prints the latest record of each journaled transaction matching the filters.
+pub fn run_history_command(
+    data_dir: &Path,
+    request_id: Option<&str>,
+    role: Option<&str>,
+    status: Option<TxStatus>,
+    limit: usize,
+) -> Result<(), Box<dyn std::error::Error>> {
+    // Keep the first-seen order of each hash, with the state of its last record
+    let mut order = Vec::new();
+    let mut latest: HashMap<String, JournalRecord> = HashMap::new();
+    for record in journal::read_journal(data_dir)? {
+        if !latest.contains_key(&record.tx_hash) {
+            order.push(record.tx_hash.clone());
+        }
+        latest.insert(record.tx_hash.clone(), record);
+    }
+
+    let records: Vec<&JournalRecord> = order
+        .iter()
+        .map(|tx_hash| &latest[tx_hash])
+        .filter(|record| request_id.is_none_or(|id| record.request_id.as_deref() == Some(id)))
+        .filter(|record| role.is_none_or(|role| record.role.as_deref() == Some(role)))
+        .filter(|record| status.is_none_or(|status| record.status == status))
+        .collect();
+
+    if records.is_empty() {
+        println!("No transactions found");
+        return Ok(());
+    }
+
+    for record in &records[records.len().saturating_sub(limit)..] {
+        println!(
+            "{} {:?} {} {} nonce {} {} request {} ({})",
+            record.timestamp,
+            record.status,
+            record.tx_hash,
+            record.signer_id,
+            record.nonce,
+            record.method,
+            record.request_id.as_deref().unwrap_or("-"),
+            record.role.as_deref().unwrap_or("-"),
+        );
+        for log in &record.logs {
+            println!("  log: {}", log);
+        }
+        if let Some(error) = &record.error {
+            println!("  error: {}", error);
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,58 @@
+use near_crypto::SecretKey;
+
+use crate::cli::{Cli, KeysCommand};
+use crate::credentials;
+use crate::keystore::{self, Keystore};
+
+pub fn run_keys_command(
+    command: &KeysCommand,
+    cli: &Cli,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let keystore = Keystore::open(cli.keystore_dir.as_deref())?;
+
+    match command {
+        KeysCommand::Import {
+            account_id,
+            from_file,
+        } => {
+            let secret_key = match from_file {
+                Some(path) => credentials::read_key_file(account_id, path)?,
+                None => rpassword::prompt_password("Secret key: ")?
+                    .trim()
+                    .parse::<SecretKey>()?,
+            };
+
+            let passphrase = match &cli.passphrase_file {
+                Some(path) => keystore::read_passphrase(Some(path), "")?,
+                None => {
+                    let passphrase = rpassword::prompt_password("New passphrase: ")?;
+                    if passphrase != rpassword::prompt_password("Repeat passphrase: ")? {
+                        return Err("Passphrases do not match".into());
+                    }
+                    passphrase
+                }
+            };
+            if passphrase.is_empty() {
+                return Err("Passphrase must not be empty".into());
+            }
+
+            let path = keystore.import(account_id, &secret_key, &passphrase)?;
+            println!(
+                "Stored {} for {} in {}",
+                secret_key.public_key(),
+                account_id,
+                path.display()
+            );
+        }
+        KeysCommand::List => {
+            for entry in keystore.list()? {
+                println!("{}\t{}", entry.account_id, entry.public_key);
+            }
+        }
+        KeysCommand::ExportPublic { account_id } => {
+            println!("{}", keystore.read(account_id)?.public_key);
+        }
+    }
+
+    Ok(())
+}
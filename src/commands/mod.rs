@@ -0,0 +1,10 @@
+mod account;
+mod history;
+mod keys;
+mod offline;
+mod operator;
+
+pub use history::run_history_command;
+pub use keys::run_keys_command;
+pub use offline::{run_broadcast_command, run_sign_command};
+pub use operator::{run_operator_command, run_stage_command};
@@ -0,0 +1,97 @@
+use near_jsonrpc_client::methods;
+use near_primitives::types::{BlockId, BlockReference, Finality};
+use std::path::Path;
+
+use crate::cli::Cli;
+use crate::config::Config;
+use crate::constants::TRANSACTION_VALIDITY_PERIOD;
+use crate::credentials;
+use crate::offline;
+use crate::rpc_pool::RpcPool;
+use crate::signer::sign_transaction;
+use crate::tx_sender::TxSender;
+
+/// Signs an exported transaction with the key of its signer. Makes no RPC calls, though a
+/// `--remote-signer` is still reached over its socket or URL.
+pub async fn run_sign_command(
+    input: &Path,
+    out: Option<&Path>,
+    cli: &Cli,
+    config: &Config,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let transaction = offline::read_unsigned(input)?;
+
+    let signer = credentials::resolve_signer(cli, &transaction.signer_id, || {
+        credentials::load_signer(cli, &transaction.signer_id, config.network)
+    })
+    .await?;
+    if signer.public_key() != &transaction.public_key {
+        return Err(format!(
+            "Transaction is for key {}, but the key found for {} is {}",
+            transaction.public_key,
+            transaction.signer_id,
+            signer.public_key()
+        )
+        .into());
+    }
+
+    // The file could have been altered on its way here, so show what is actually signed
+    println!("Signing transaction {}", transaction.get_hash_and_size().0);
+    offline::print_details(&transaction);
+
+    let signed_transaction = sign_transaction(signer.as_ref(), transaction).await?;
+    let out = match out {
+        Some(path) => path.to_path_buf(),
+        None => input.with_file_name(format!("{}.signed.json", signed_transaction.get_hash())),
+    };
+    offline::write_signed(&out, &signed_transaction)?;
+
+    println!("Signed transaction written to {}", out.display());
+    Ok(())
+}
+
+/// Submits a transaction signed offline, after checking its block hash is still valid.
+pub async fn run_broadcast_command(
+    input: &Path,
+    tx_sender: &TxSender,
+    client: &RpcPool,
+    config: &Config,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let signed_transaction = offline::read_signed(input)?;
+    let block_hash = signed_transaction.transaction.block_hash;
+
+    // Old blocks are garbage collected, so a missing block has expired as well
+    let block = client
+        .call(methods::block::RpcBlockRequest {
+            block_reference: BlockReference::BlockId(BlockId::Hash(block_hash)),
+        })
+        .await
+        .map_err(|err| format!("Block hash {} is expired or unknown: {}", block_hash, err))?;
+    let final_block = client
+        .call(methods::block::RpcBlockRequest {
+            block_reference: BlockReference::Finality(Finality::Final),
+        })
+        .await?;
+
+    let age = final_block
+        .header
+        .height
+        .saturating_sub(block.header.height);
+    if age >= TRANSACTION_VALIDITY_PERIOD {
+        return Err(format!(
+            "Block hash {} is {} blocks old and has expired; export and sign the transaction again",
+            block_hash, age
+        )
+        .into());
+    }
+
+    let tx_response = tx_sender
+        .broadcast(signed_transaction, config.wait_until.into())
+        .await?;
+    println!(
+        "Transaction status: {:?}",
+        tx_response.final_execution_status
+    );
+
+    Ok(())
+}
@@ -0,0 +1,84 @@
+use near_event_listener::EventLog;
+use near_sdk::AccountId;
+use std::sync::Arc;
+
+use super::account::run_account_command;
+use crate::cli::Command;
+use crate::config::Config;
+use crate::key_pool::AccessKeyPool;
+use crate::processors::{
+    obtain_top_ten, query_stage, Aggregator, Miner, TransactionProcessor, Validator,
+};
+use crate::rpc_pool::RpcPool;
+use crate::tx_sender::TxSender;
+
+pub async fn run_stage_command(
+    client: Arc<RpcPool>,
+    config: &Config,
+    start_time: u64,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let stage = query_stage(client, config, start_time).await?;
+    println!("Current Stage: {}", stage.trim_matches('"'));
+
+    Ok(())
+}
+
+/// Runs a single commit, reveal or aggregation step against one request, without the listener.
+pub async fn run_operator_command(
+    command: &Command,
+    key_pool: Arc<AccessKeyPool>,
+    tx_sender: Arc<TxSender>,
+    account_id: AccountId,
+    config: Arc<Config>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    match command {
+        Command::CommitMiner { request_id } => {
+            Miner::new(key_pool, tx_sender, account_id, config)
+                .commit(request_event(request_id))
+                .await?
+        }
+        Command::RevealMiner { request_id } => {
+            Miner::new(key_pool, tx_sender, account_id, config)
+                .reveal(request_event(request_id))
+                .await?
+        }
+        Command::CommitValidator { request_id } => {
+            Validator::new(key_pool, tx_sender, account_id, config)
+                .commit(request_event(request_id))
+                .await?
+        }
+        Command::RevealValidator { request_id } => {
+            Validator::new(key_pool, tx_sender, account_id, config)
+                .reveal(request_event(request_id))
+                .await?
+        }
+        Command::TopTen { request_id } => {
+            let aggregator = Aggregator::new(key_pool, tx_sender, account_id, config);
+            obtain_top_ten(&aggregator, request_event(request_id)).await?
+        }
+        Command::Account(account_command) => {
+            run_account_command(account_command, &key_pool, &tx_sender, &account_id, &config)
+                .await?
+        }
+        Command::Keys(_)
+        | Command::Stage { .. }
+        | Command::Sign { .. }
+        | Command::Broadcast { .. }
+        | Command::History { .. } => {
+            return Err("Not an operator command".into());
+        }
+    }
+
+    println!("Done");
+    Ok(())
+}
+
+/// Stand-in for the event the listener would have delivered for `request_id`.
+fn request_event(request_id: &str) -> EventLog {
+    EventLog {
+        standard: String::new(),
+        version: String::new(),
+        event: String::new(),
+        data: serde_json::json!([{ "request_id": request_id }]),
+    }
+}
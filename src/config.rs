@@ -0,0 +1,568 @@
+use near_sdk::AccountId;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::cli::{Cli, Modes, Networks, WaitLevel};
+use crate::constants::*;
+use crate::processors::AnswerStrategy;
+use crate::tx_sender::RetryPolicy;
+
+/// Raw contents of the TOML config file. Every key is optional.
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FileConfig {
+    pub network: Option<Networks>,
+    pub rpc_url: Option<String>,
+    pub rpc_fallback_urls: Vec<String>,
+    pub health_check_interval_secs: Option<u64>,
+    pub account_to_listen: Option<String>,
+    pub function_to_listen: Option<String>,
+    pub protocol_contract: Option<String>,
+    pub timeout_secs: Option<u64>,
+    pub poll_interval_secs: Option<u64>,
+    pub stage_attempts: Option<u32>,
+    pub retry_max_attempts: Option<u32>,
+    pub retry_base_delay_ms: Option<u64>,
+    pub retry_max_delay_ms: Option<u64>,
+    pub retry_deadline_secs: Option<u64>,
+    pub wait_until: Option<WaitLevel>,
+    pub relayer_url: Option<String>,
+    pub data_dir: Option<PathBuf>,
+    pub checkpoint_interval_secs: Option<u64>,
+    pub access_key_files: Vec<PathBuf>,
+    pub methods: HashMap<String, FileMethodProfile>,
+    pub roles: Vec<FileRole>,
+    pub fleet: Vec<FileFleetMiner>,
+}
+
+/// One `[methods.<name>]` table of the config file. The deposit is a string, as yoctoNEAR
+/// amounts do not fit a TOML integer.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FileMethodProfile {
+    pub gas: Option<u64>,
+    pub deposit: Option<String>,
+    pub wait_until: Option<WaitLevel>,
+}
+
+/// One `[[roles]]` entry of the config file.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FileRole {
+    pub mode: Modes,
+    pub account_id: String,
+    pub key_file: Option<PathBuf>,
+}
+
+/// One `[[fleet]]` miner account of the config file.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FileFleetMiner {
+    pub account_id: String,
+    pub key_file: Option<PathBuf>,
+    #[serde(default)]
+    pub answer: AnswerStrategy,
+}
+
+impl FileConfig {
+    pub fn from_path(path: &Path) -> Result<Self, ConfigError> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|err| ConfigError::Read(path.to_path_buf(), err))?;
+
+        toml::from_str(&contents).map_err(|err| ConfigError::Parse(path.to_path_buf(), err))
+    }
+}
+
+/// Resolved client configuration.
+///
+/// Precedence: command-line flag, then environment variable, then config file, then the
+/// defaults in `constants.rs`.
+pub struct Config {
+    pub network: Networks,
+    pub rpc_url: String,
+    pub rpc_fallback_urls: Vec<String>,
+    pub health_check_interval: Duration,
+    /// Only needed by the listener and the protocol commands; read it through
+    /// `account_to_listen()`
+    pub account_to_listen: Option<AccountId>,
+    pub function_to_listen: String,
+    pub protocol_contract: AccountId,
+    /// How long a single RPC call may take before its endpoint counts as failed
+    pub timeout: Duration,
+    pub poll_interval: Duration,
+    pub stage_attempts: u32,
+    pub retry_max_attempts: u32,
+    pub retry_base_delay: Duration,
+    pub retry_max_delay: Duration,
+    /// Overall time a transaction is retried and its status polled for
+    pub retry_deadline: Duration,
+    pub wait_until: WaitLevel,
+    /// Relayer that pays for commit and reveal calls sent as NEP-366 meta transactions
+    pub relayer_url: Option<String>,
+    pub data_dir: PathBuf,
+    pub checkpoint_interval: Duration,
+    /// Extra access keys; each signing account uses the ones that belong to it
+    pub access_key_files: Vec<PathBuf>,
+    pub method_profiles: HashMap<String, MethodProfile>,
+    pub roles: Vec<RoleConfig>,
+    pub fleet: Vec<FleetMinerConfig>,
+}
+
+/// Gas and deposit attached to calls of one contract method, and how long to wait for them.
+#[derive(Copy, Clone)]
+pub struct MethodProfile {
+    pub gas: u64,
+    /// yoctoNEAR
+    pub deposit: u128,
+    pub wait_until: WaitLevel,
+}
+
+/// A role run by this process, with the account that signs its transactions.
+pub struct RoleConfig {
+    pub mode: Modes,
+    pub account_id: AccountId,
+    pub key_file: Option<PathBuf>,
+}
+
+/// A miner account driven by the fleet.
+pub struct FleetMinerConfig {
+    pub account_id: AccountId,
+    pub key_file: Option<PathBuf>,
+    pub answer: AnswerStrategy,
+}
+
+impl Config {
+    /// The configured profile of `method_name`, or 100 Tgas, no deposit and `wait_until`.
+    pub fn method_profile(&self, method_name: &str) -> MethodProfile {
+        self.method_profiles
+            .get(method_name)
+            .copied()
+            .unwrap_or(MethodProfile {
+                gas: DEFAULT_GAS,
+                deposit: 0,
+                wait_until: self.wait_until,
+            })
+    }
+
+    /// The account whose events are listened to and whose view methods are queried.
+    pub fn account_to_listen(&self) -> Result<&AccountId, ConfigError> {
+        self.account_to_listen.as_ref().ok_or_else(|| {
+            ConfigError::invalid(
+                "account_to_listen",
+                "is required, as the network has no preset for it",
+            )
+        })
+    }
+
+    /// Transaction retries, bounded overall by `retry_deadline`.
+    pub fn retry_policy(&self) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: self.retry_max_attempts,
+            base_delay: self.retry_base_delay,
+            max_delay: self.retry_max_delay,
+            deadline: self.retry_deadline,
+        }
+    }
+
+    /// The primary endpoint followed by the fallbacks.
+    pub fn rpc_urls(&self) -> Vec<String> {
+        std::iter::once(self.rpc_url.clone())
+            .chain(self.rpc_fallback_urls.iter().cloned())
+            .collect()
+    }
+
+    pub fn resolve(cli: &Cli) -> Result<Self, ConfigError> {
+        let file = match &cli.config {
+            Some(path) => FileConfig::from_path(path)?,
+            None => FileConfig::default(),
+        };
+
+        let network = cli.network.or(file.network).unwrap_or(Networks::Testnet);
+
+        let rpc_url = required(
+            "rpc_url",
+            cli.rpc_url.clone().or(file.rpc_url),
+            default_rpc_url(network),
+        )?;
+        check_http_url("rpc_url", &rpc_url)?;
+
+        let rpc_fallback_urls = if !cli.rpc_fallback_urls.is_empty() {
+            cli.rpc_fallback_urls.clone()
+        } else {
+            file.rpc_fallback_urls
+        };
+        for (i, url) in rpc_fallback_urls.iter().enumerate() {
+            check_http_url(format!("rpc_fallback_urls[{}]", i), url)?;
+        }
+
+        let health_check_interval = positive_secs(
+            "health_check_interval_secs",
+            cli.health_check_interval_secs
+                .or(file.health_check_interval_secs),
+            DEFAULT_HEALTH_CHECK_INTERVAL,
+        )?;
+
+        // Checked when used, so that `sign`, `broadcast` and `history` work without it
+        let account_to_listen = cli
+            .account_to_listen
+            .clone()
+            .or(file.account_to_listen)
+            .or_else(|| default_account_to_listen(network).map(str::to_string))
+            .map(|value| parse_account_id("account_to_listen", value))
+            .transpose()?;
+
+        let function_to_listen = cli
+            .function_to_listen
+            .clone()
+            .or(file.function_to_listen)
+            .unwrap_or_else(|| FUNCTION_TO_LISTEN.to_string());
+        if function_to_listen.is_empty() {
+            return Err(ConfigError::invalid(
+                "function_to_listen",
+                "must not be empty",
+            ));
+        }
+
+        let protocol_contract = parse_account_id(
+            "protocol_contract",
+            required(
+                "protocol_contract",
+                cli.protocol_contract.clone().or(file.protocol_contract),
+                default_protocol_contract(network),
+            )?,
+        )?;
+
+        let timeout = positive_secs(
+            "timeout_secs",
+            cli.timeout_secs.or(file.timeout_secs),
+            DEFAULT_TIMEOUT,
+        )?;
+
+        let poll_interval = positive_secs(
+            "poll_interval_secs",
+            cli.poll_interval_secs.or(file.poll_interval_secs),
+            DEFAULT_POLL_INTERVAL,
+        )?;
+
+        let stage_attempts = cli
+            .stage_attempts
+            .or(file.stage_attempts)
+            .unwrap_or(DEFAULT_STAGE_ATTEMPTS);
+        if stage_attempts == 0 {
+            return Err(ConfigError::invalid(
+                "stage_attempts",
+                "must be greater than 0",
+            ));
+        }
+
+        let retry_max_attempts = cli
+            .retry_max_attempts
+            .or(file.retry_max_attempts)
+            .unwrap_or(DEFAULT_RETRY_MAX_ATTEMPTS);
+        if retry_max_attempts == 0 {
+            return Err(ConfigError::invalid(
+                "retry_max_attempts",
+                "must be greater than 0",
+            ));
+        }
+
+        let retry_base_delay = positive_millis(
+            "retry_base_delay_ms",
+            cli.retry_base_delay_ms.or(file.retry_base_delay_ms),
+            DEFAULT_RETRY_BASE_DELAY,
+        )?;
+
+        let retry_max_delay = positive_millis(
+            "retry_max_delay_ms",
+            cli.retry_max_delay_ms.or(file.retry_max_delay_ms),
+            DEFAULT_RETRY_MAX_DELAY,
+        )?;
+        if retry_max_delay < retry_base_delay {
+            return Err(ConfigError::invalid(
+                "retry_max_delay_ms",
+                "must not be less than retry_base_delay_ms",
+            ));
+        }
+
+        let retry_deadline = positive_secs(
+            "retry_deadline_secs",
+            cli.retry_deadline_secs.or(file.retry_deadline_secs),
+            DEFAULT_RETRY_DEADLINE,
+        )?;
+        // A single RPC call may take up to `timeout`, so a shorter deadline leaves no retries
+        if retry_deadline < timeout {
+            return Err(ConfigError::invalid(
+                "retry_deadline_secs",
+                "must not be less than timeout_secs",
+            ));
+        }
+
+        let wait_until = cli
+            .wait_until
+            .or(file.wait_until)
+            .unwrap_or(WaitLevel::Final);
+
+        let relayer_url = cli.relayer_url.clone().or(file.relayer_url);
+        if let Some(url) = &relayer_url {
+            check_http_url("relayer_url", url)?;
+        }
+
+        let data_dir = cli
+            .data_dir
+            .clone()
+            .or(file.data_dir)
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_DATA_DIR));
+
+        let checkpoint_interval = positive_secs(
+            "checkpoint_interval_secs",
+            cli.checkpoint_interval_secs
+                .or(file.checkpoint_interval_secs),
+            DEFAULT_CHECKPOINT_INTERVAL,
+        )?;
+
+        let access_key_files = if !cli.access_key_files.is_empty() {
+            cli.access_key_files.clone()
+        } else {
+            file.access_key_files
+        };
+
+        let method_profiles = file
+            .methods
+            .into_iter()
+            .map(|(method_name, profile)| {
+                let gas = match profile.gas {
+                    Some(0) => {
+                        return Err(ConfigError::invalid(
+                            format!("methods.{}.gas", method_name),
+                            "must be greater than 0",
+                        ))
+                    }
+                    Some(gas) => gas,
+                    None => DEFAULT_GAS,
+                };
+                let deposit = match profile.deposit {
+                    Some(deposit) => deposit.parse::<u128>().map_err(|err| {
+                        ConfigError::invalid(
+                            format!("methods.{}.deposit", method_name),
+                            format!("`{}`: {}", deposit, err),
+                        )
+                    })?,
+                    None => 0,
+                };
+                Ok((
+                    method_name,
+                    MethodProfile {
+                        gas,
+                        deposit,
+                        wait_until: profile.wait_until.unwrap_or(wait_until),
+                    },
+                ))
+            })
+            .collect::<Result<HashMap<_, _>, ConfigError>>()?;
+
+        let roles = if !cli.roles.is_empty() {
+            cli.roles
+                .iter()
+                .map(|(mode, account_id)| RoleConfig {
+                    mode: *mode,
+                    account_id: account_id.clone(),
+                    key_file: None,
+                })
+                .collect()
+        } else {
+            file.roles
+                .into_iter()
+                .enumerate()
+                .map(|(i, role)| {
+                    Ok(RoleConfig {
+                        mode: role.mode,
+                        account_id: parse_account_id(
+                            format!("roles[{}].account_id", i),
+                            role.account_id,
+                        )?,
+                        key_file: role.key_file,
+                    })
+                })
+                .collect::<Result<Vec<_>, ConfigError>>()?
+        };
+        let fleet = file
+            .fleet
+            .into_iter()
+            .enumerate()
+            .map(|(i, miner)| {
+                Ok(FleetMinerConfig {
+                    account_id: parse_account_id(
+                        format!("fleet[{}].account_id", i),
+                        miner.account_id,
+                    )?,
+                    key_file: miner.key_file,
+                    answer: miner.answer,
+                })
+            })
+            .collect::<Result<Vec<_>, ConfigError>>()?;
+
+        // Two signers sharing an account would race on the same nonce
+        let accounts = roles
+            .iter()
+            .enumerate()
+            .map(|(i, role)| (format!("roles[{}].account_id", i), &role.account_id))
+            .chain(
+                fleet
+                    .iter()
+                    .enumerate()
+                    .map(|(i, miner)| (format!("fleet[{}].account_id", i), &miner.account_id)),
+            )
+            .collect::<Vec<_>>();
+        for (i, (key, account_id)) in accounts.iter().enumerate() {
+            if accounts[..i].iter().any(|(_, other)| other == account_id) {
+                return Err(ConfigError::invalid(
+                    key.clone(),
+                    format!("`{}` is used more than once", account_id),
+                ));
+            }
+        }
+
+        Ok(Self {
+            network,
+            rpc_url,
+            rpc_fallback_urls,
+            health_check_interval,
+            account_to_listen,
+            function_to_listen,
+            protocol_contract,
+            timeout,
+            poll_interval,
+            stage_attempts,
+            retry_max_attempts,
+            retry_base_delay,
+            retry_max_delay,
+            retry_deadline,
+            wait_until,
+            relayer_url,
+            data_dir,
+            checkpoint_interval,
+            access_key_files,
+            method_profiles,
+            roles,
+            fleet,
+        })
+    }
+}
+
+fn default_rpc_url(network: Networks) -> Option<&'static str> {
+    match network {
+        Networks::Testnet => Some(NEAR_RPC_TESTNET),
+        Networks::Mainnet => Some(NEAR_RPC_MAINNET),
+        Networks::Localnet => Some(NEAR_RPC_LOCALNET),
+        Networks::Custom => None,
+    }
+}
+
+fn default_account_to_listen(network: Networks) -> Option<&'static str> {
+    match network {
+        Networks::Testnet => Some(ACCOUNT_TO_LISTEN),
+        Networks::Localnet => Some(ACCOUNT_TO_LISTEN_LOCALNET),
+        // No mainnet deployment emits the request events yet
+        Networks::Mainnet | Networks::Custom => None,
+    }
+}
+
+fn default_protocol_contract(network: Networks) -> Option<&'static str> {
+    match network {
+        Networks::Testnet => Some(EARTHMIND_PROTOCOL_CONTRACT_TESTNET),
+        Networks::Mainnet => Some(EARTHMIND_PROTOCOL_CONTRACT_MAINNET),
+        Networks::Localnet => Some(EARTHMIND_PROTOCOL_CONTRACT_LOCALNET),
+        Networks::Custom => None,
+    }
+}
+
+/// Falls back to the network preset, which `Custom` does not have.
+fn required(
+    key: &'static str,
+    value: Option<String>,
+    default: Option<&'static str>,
+) -> Result<String, ConfigError> {
+    value
+        .or_else(|| default.map(str::to_string))
+        .ok_or_else(|| {
+            ConfigError::invalid(key, "is required, as the network has no preset for it")
+        })
+}
+
+fn parse_account_id(key: impl Into<String>, value: String) -> Result<AccountId, ConfigError> {
+    value
+        .parse()
+        .map_err(|err| ConfigError::invalid(key, format!("`{}`: {}", value, err)))
+}
+
+fn check_http_url(key: impl Into<String>, url: &str) -> Result<(), ConfigError> {
+    if url.starts_with("http://") || url.starts_with("https://") {
+        Ok(())
+    } else {
+        Err(ConfigError::invalid(
+            key,
+            format!("`{}` is not an http(s) URL", url),
+        ))
+    }
+}
+
+fn positive_secs(
+    key: &'static str,
+    value: Option<u64>,
+    default: Duration,
+) -> Result<Duration, ConfigError> {
+    match value {
+        Some(0) => Err(ConfigError::invalid(key, "must be greater than 0")),
+        Some(secs) => Ok(Duration::from_secs(secs)),
+        None => Ok(default),
+    }
+}
+
+fn positive_millis(
+    key: &'static str,
+    value: Option<u64>,
+    default: Duration,
+) -> Result<Duration, ConfigError> {
+    match value {
+        Some(0) => Err(ConfigError::invalid(key, "must be greater than 0")),
+        Some(millis) => Ok(Duration::from_millis(millis)),
+        None => Ok(default),
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Read(PathBuf, std::io::Error),
+    Parse(PathBuf, toml::de::Error),
+    Invalid { key: String, reason: String },
+}
+
+impl ConfigError {
+    fn invalid(key: impl Into<String>, reason: impl Into<String>) -> Self {
+        ConfigError::Invalid {
+            key: key.into(),
+            reason: reason.into(),
+        }
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Read(path, err) => {
+                write!(f, "failed to read config file {}: {}", path.display(), err)
+            }
+            ConfigError::Parse(path, err) => {
+                write!(f, "failed to parse config file {}: {}", path.display(), err)
+            }
+            ConfigError::Invalid { key, reason } => {
+                write!(f, "invalid config value for `{}`: {}", key, reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
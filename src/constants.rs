@@ -2,8 +2,46 @@ use std::time::Duration;
 
 pub const NEAR_RPC_TESTNET: &str = "https://rpc.testnet.pagoda.co";
 pub const NEAR_RPC_MAINNET: &str = "https://rpc.mainnet.pagoda.co";
+pub const NEAR_RPC_LOCALNET: &str = "http://127.0.0.1:3030";
 pub const ACCOUNT_TO_LISTEN: &str = "earthmind2.testnet";
+pub const ACCOUNT_TO_LISTEN_LOCALNET: &str = "earthmind.test.near";
 pub const FUNCTION_TO_LISTEN: &str = "request_governance_decision";
 pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(10);
+pub const DEFAULT_STAGE_ATTEMPTS: u32 = 30;
 pub const EARTHMIND_PROTOCOL_CONTRACT_TESTNET: &str = "earthmindprotocol.testnet";
 pub const EARTHMIND_PROTOCOL_CONTRACT_MAINNET: &str = "earthmindprotocol.near";
+pub const EARTHMIND_PROTOCOL_CONTRACT_LOCALNET: &str = "earthmindprotocol.test.near";
+pub const PRIVATE_KEY_ENV: &str = "EARTHMIND_PRIVATE_KEY";
+pub const NEAR_CREDENTIALS_DIR: &str = ".near-credentials";
+pub const KEYSTORE_DIR: &str = ".earthmind/keystore";
+pub const SCRYPT_LOG_N: u8 = 17;
+pub const SCRYPT_R: u32 = 8;
+pub const SCRYPT_P: u32 = 1;
+pub const DEFAULT_DATA_DIR: &str = "data";
+pub const CHECKPOINT_FILE: &str = "checkpoint";
+pub const DEFAULT_CHECKPOINT_INTERVAL: Duration = Duration::from_secs(5);
+pub const BLOCK_POLL_INTERVAL: Duration = Duration::from_secs(1);
+pub const JOURNAL_FILE: &str = "transactions.jsonl";
+pub const DEFAULT_HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+pub const RPC_COOLDOWN_BASE: Duration = Duration::from_secs(5);
+pub const RPC_COOLDOWN_MAX: Duration = Duration::from_secs(300);
+pub const MAX_RESIGN_ATTEMPTS: u32 = 3;
+pub const NONCE_RESYNC_INTERVAL: Duration = Duration::from_secs(300);
+// Transactions expire after the chain's validity period (about a day), so this is well clear of it
+pub const BLOCK_HASH_MAX_AGE: Duration = Duration::from_secs(600);
+// Blocks after which the chain rejects a transaction's block hash
+pub const TRANSACTION_VALIDITY_PERIOD: u64 = 86400;
+// Blocks a delegate action stays valid for after it is signed
+pub const DELEGATE_ACTION_TTL: u64 = 200;
+// Covers a relayer that waits for its transaction to become final before answering
+pub const RELAYER_TIMEOUT: Duration = Duration::from_secs(60);
+// A signer that takes longer is treated as down, so the key it holds is released
+pub const SIGNER_TIMEOUT: Duration = Duration::from_secs(30);
+pub const DEFAULT_GAS: u64 = 100_000_000_000_000;
+pub const FINALITY_TRACK_TIMEOUT: Duration = Duration::from_secs(120);
+pub const DEFAULT_RETRY_MAX_ATTEMPTS: u32 = 5;
+pub const DEFAULT_RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+pub const DEFAULT_RETRY_MAX_DELAY: Duration = Duration::from_secs(5);
+// Leaves room for several status polls after an RPC timeout
+pub const DEFAULT_RETRY_DEADLINE: Duration = Duration::from_secs(120);
@@ -0,0 +1,218 @@
+use near_crypto::{InMemorySigner, KeyFile, SecretKey};
+use near_sdk::AccountId;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::cli::{Cli, Networks};
+use crate::constants::{NEAR_CREDENTIALS_DIR, PRIVATE_KEY_ENV};
+use crate::keystore::{self, Keystore};
+use crate::signer::{RemoteSigner, TxSigner};
+
+/// Builds the signer from the first key source found, in this order:
+/// `--key-file`, `EARTHMIND_PRIVATE_KEY`, the encrypted keystore,
+/// `<credentials-dir>/<network>/<account>.json` and finally `--private-key`.
+pub fn load_signer(
+    cli: &Cli,
+    account_id: &AccountId,
+    network: Networks,
+) -> Result<InMemorySigner, Box<dyn std::error::Error>> {
+    if let Some(path) = &cli.key_file {
+        println!("Loading key from file {}", path.display());
+        return signer_from_key_file(account_id, path);
+    }
+
+    if let Ok(value) = std::env::var(PRIVATE_KEY_ENV) {
+        println!("Loading key from {}", PRIVATE_KEY_ENV);
+        let secret_key: SecretKey = value
+            .trim()
+            .parse()
+            .map_err(|err| format!("Invalid secret key in {}: {}", PRIVATE_KEY_ENV, err))?;
+        return Ok(InMemorySigner::from_secret_key(
+            account_id.clone(),
+            secret_key,
+        ));
+    }
+
+    if let Some(signer) = signer_from_stores(cli, account_id, network)? {
+        return Ok(signer);
+    }
+
+    if let Some(secret_key) = &cli.private_key {
+        println!("Loading key from --private-key");
+        return Ok(InMemorySigner::from_secret_key(
+            account_id.clone(),
+            secret_key.clone(),
+        ));
+    }
+
+    Err(format!(
+        "No signing key found for {}: pass --key-file, set {}, import it with `keys import` or add NEAR credentials",
+        account_id, PRIVATE_KEY_ENV
+    )
+    .into())
+}
+
+/// Signer for one of several configured accounts (roles or fleet miners). The process-wide
+/// `EARTHMIND_PRIVATE_KEY` and `--private-key` sources are ignored here, as they cannot serve
+/// more than one account.
+pub fn load_account_signer(
+    cli: &Cli,
+    account_id: &AccountId,
+    key_file: Option<&Path>,
+    network: Networks,
+) -> Result<InMemorySigner, Box<dyn std::error::Error>> {
+    if let Some(path) = key_file {
+        println!(
+            "Loading key for {} from file {}",
+            account_id,
+            path.display()
+        );
+        return signer_from_key_file(account_id, path);
+    }
+
+    signer_from_stores(cli, account_id, network)?.ok_or_else(|| {
+        format!(
+            "No signing key found for {}: set its `key_file`, import it with `keys import` or add NEAR credentials",
+            account_id
+        )
+        .into()
+    })
+}
+
+/// The signer for `account_id`: the `--remote-signer` when one is set, which then replaces
+/// every local key source, otherwise the local key found by `local`.
+pub async fn resolve_signer(
+    cli: &Cli,
+    account_id: &AccountId,
+    local: impl FnOnce() -> Result<InMemorySigner, Box<dyn std::error::Error>>,
+) -> Result<Arc<dyn TxSigner>, Box<dyn std::error::Error>> {
+    match &cli.remote_signer {
+        Some(endpoint) => {
+            let signer = RemoteSigner::connect(endpoint.clone(), account_id.clone()).await?;
+            println!(
+                "Signing for {} with remote key {}",
+                account_id,
+                signer.public_key()
+            );
+            Ok(Arc::new(signer))
+        }
+        None => Ok(Arc::new(local()?)),
+    }
+}
+
+/// Extra access keys of `account_id` for its key pool. Key files of other accounts are
+/// skipped, so one list can serve every account the process signs for.
+pub fn load_access_keys(
+    account_id: &AccountId,
+    paths: &[PathBuf],
+) -> Result<Vec<InMemorySigner>, Box<dyn std::error::Error>> {
+    let mut signers = Vec::new();
+
+    for path in paths {
+        let key_file = KeyFile::from_file(path)
+            .map_err(|err| format!("Failed to read key file {}: {}", path.display(), err))?;
+        if &key_file.account_id != account_id {
+            continue;
+        }
+
+        println!(
+            "Adding access key for {} from file {}",
+            account_id,
+            path.display()
+        );
+        signers.push(signer_from_key_file(account_id, path)?);
+    }
+
+    Ok(signers)
+}
+
+/// Looks the account up in the encrypted keystore, then in the NEAR credentials directory.
+fn signer_from_stores(
+    cli: &Cli,
+    account_id: &AccountId,
+    network: Networks,
+) -> Result<Option<InMemorySigner>, Box<dyn std::error::Error>> {
+    let keystore = Keystore::open(cli.keystore_dir.as_deref())?;
+    if keystore.contains(account_id) {
+        println!("Unlocking key for {} from keystore", account_id);
+        let passphrase = keystore::read_passphrase(
+            cli.passphrase_file.as_deref(),
+            &format!("Passphrase for {}: ", account_id),
+        )?;
+        let secret_key = keystore.unlock(account_id, &passphrase)?;
+        return Ok(Some(InMemorySigner::from_secret_key(
+            account_id.clone(),
+            secret_key,
+        )));
+    }
+
+    if let Some(path) = credentials_path(cli.credentials_dir.as_deref(), network, account_id) {
+        if path.exists() {
+            println!("Loading key from credentials {}", path.display());
+            return signer_from_key_file(account_id, &path).map(Some);
+        }
+    }
+
+    Ok(None)
+}
+
+fn signer_from_key_file(
+    account_id: &AccountId,
+    path: &Path,
+) -> Result<InMemorySigner, Box<dyn std::error::Error>> {
+    let secret_key = read_key_file(account_id, path)?;
+    Ok(InMemorySigner::from_secret_key(
+        account_id.clone(),
+        secret_key,
+    ))
+}
+
+/// Reads a NEAR CLI style key file, checking that it belongs to `account_id`.
+pub fn read_key_file(
+    account_id: &AccountId,
+    path: &Path,
+) -> Result<SecretKey, Box<dyn std::error::Error>> {
+    let key_file = KeyFile::from_file(path)
+        .map_err(|err| format!("Failed to read key file {}: {}", path.display(), err))?;
+
+    if &key_file.account_id != account_id {
+        return Err(format!(
+            "Key file {} belongs to {}, expected {}",
+            path.display(),
+            key_file.account_id,
+            account_id
+        )
+        .into());
+    }
+
+    if key_file.secret_key.public_key() != key_file.public_key {
+        return Err(format!(
+            "Key file {} has a public key that does not match its private key",
+            path.display()
+        )
+        .into());
+    }
+
+    Ok(key_file.secret_key)
+}
+
+/// `Custom` networks have no well-known credentials directory.
+fn credentials_path(
+    credentials_dir: Option<&Path>,
+    network: Networks,
+    account_id: &AccountId,
+) -> Option<PathBuf> {
+    let network_dir = match network {
+        Networks::Testnet => "testnet",
+        Networks::Mainnet => "mainnet",
+        Networks::Localnet => "localnet",
+        Networks::Custom => return None,
+    };
+
+    let base = match credentials_dir {
+        Some(dir) => dir.to_path_buf(),
+        None => PathBuf::from(std::env::var_os("HOME")?).join(NEAR_CREDENTIALS_DIR),
+    };
+
+    Some(base.join(network_dir).join(format!("{}.json", account_id)))
+}
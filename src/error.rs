@@ -0,0 +1,162 @@
+use near_jsonrpc_client::errors::{JsonRpcError, JsonRpcServerError};
+use near_jsonrpc_primitives::types::blocks::RpcBlockError;
+use near_jsonrpc_primitives::types::chunks::RpcChunkError;
+use near_jsonrpc_primitives::types::query::RpcQueryError;
+use near_jsonrpc_primitives::types::transactions::RpcTransactionError;
+use near_primitives::errors::InvalidTxError;
+use std::fmt;
+
+use crate::config::ConfigError;
+
+/// Errors returned by the client's RPC, transaction and processor APIs.
+#[derive(Debug)]
+pub enum ClientError {
+    /// The RPC endpoint could not be reached or failed outside the request handler
+    RpcTransport(String),
+    /// The RPC node handled the request and rejected it
+    RpcHandler(String),
+    /// The node returned a response of an unexpected shape
+    UnexpectedResponse(String),
+    InvalidNonce {
+        tx_nonce: u64,
+        ak_nonce: u64,
+    },
+    /// The transaction's block hash is too old to be accepted
+    ExpiredTransaction,
+    /// A view call, or the transaction or one of its receipts, failed in the contract; carries
+    /// the panic message
+    ContractPanic(String),
+    WrongStage {
+        expected: String,
+        actual: String,
+    },
+    MalformedEvent(String),
+    Timeout(String),
+    /// An unsigned transaction could not be written for offline signing
+    Export(String),
+    /// The relayer refused the delegate action
+    Relayer(String),
+    /// The signer could not be reached or refused to sign
+    Signer(String),
+    /// A setting the operation needs is missing or invalid
+    Config(String),
+}
+
+impl ClientError {
+    /// Whether the same operation may succeed if attempted again, possibly after resyncing the
+    /// nonce or fetching a fresh block hash.
+    ///
+    /// Only errors where the chain rejected the transaction count: after a transport error or a
+    /// timeout it may still execute, and sending it again could execute it twice.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            ClientError::InvalidNonce { .. } | ClientError::ExpiredTransaction => true,
+            ClientError::RpcTransport(_)
+            | ClientError::Timeout(_)
+            | ClientError::RpcHandler(_)
+            | ClientError::UnexpectedResponse(_)
+            | ClientError::ContractPanic(_)
+            | ClientError::WrongStage { .. }
+            | ClientError::MalformedEvent(_)
+            | ClientError::Export(_)
+            | ClientError::Relayer(_)
+            | ClientError::Signer(_)
+            | ClientError::Config(_) => false,
+        }
+    }
+
+    /// Maps everything except handler errors, which callers classify themselves.
+    fn from_rpc<E, F>(err: JsonRpcError<E>, handler: F) -> Self
+    where
+        E: fmt::Debug + fmt::Display,
+        F: FnOnce(E) -> ClientError,
+    {
+        match err {
+            JsonRpcError::ServerError(JsonRpcServerError::HandlerError(handler_error)) => {
+                handler(handler_error)
+            }
+            err @ JsonRpcError::ServerError(JsonRpcServerError::RequestValidationError(_)) => {
+                ClientError::RpcHandler(err.to_string())
+            }
+            err => ClientError::RpcTransport(err.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientError::RpcTransport(msg) => write!(f, "RPC transport error: {}", msg),
+            ClientError::RpcHandler(msg) => write!(f, "RPC handler error: {}", msg),
+            ClientError::UnexpectedResponse(msg) => write!(f, "unexpected RPC response: {}", msg),
+            ClientError::InvalidNonce { tx_nonce, ak_nonce } => write!(
+                f,
+                "invalid nonce {} (access key nonce is {})",
+                tx_nonce, ak_nonce
+            ),
+            ClientError::ExpiredTransaction => write!(f, "transaction expired"),
+            ClientError::ContractPanic(msg) => write!(f, "contract panicked: {}", msg),
+            ClientError::WrongStage { expected, actual } => {
+                write!(f, "wrong stage: expected {}, got {}", expected, actual)
+            }
+            ClientError::MalformedEvent(msg) => write!(f, "malformed event: {}", msg),
+            ClientError::Timeout(msg) => write!(f, "timed out: {}", msg),
+            ClientError::Export(msg) => write!(f, "export failed: {}", msg),
+            ClientError::Relayer(msg) => write!(f, "relayer rejected the delegate action: {}", msg),
+            ClientError::Signer(msg) => write!(f, "signing failed: {}", msg),
+            ClientError::Config(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {}
+
+impl From<ConfigError> for ClientError {
+    fn from(err: ConfigError) -> Self {
+        ClientError::Config(err.to_string())
+    }
+}
+
+impl From<JsonRpcError<RpcTransactionError>> for ClientError {
+    fn from(err: JsonRpcError<RpcTransactionError>) -> Self {
+        ClientError::from_rpc(err, |handler_error| match handler_error {
+            RpcTransactionError::InvalidTransaction {
+                context: InvalidTxError::InvalidNonce { tx_nonce, ak_nonce },
+            } => ClientError::InvalidNonce { tx_nonce, ak_nonce },
+            RpcTransactionError::InvalidTransaction {
+                context: InvalidTxError::Expired,
+            } => ClientError::ExpiredTransaction,
+            RpcTransactionError::TimeoutError => {
+                ClientError::Timeout("transaction was not executed in time".to_string())
+            }
+            other => ClientError::RpcHandler(other.to_string()),
+        })
+    }
+}
+
+impl From<JsonRpcError<RpcQueryError>> for ClientError {
+    fn from(err: JsonRpcError<RpcQueryError>) -> Self {
+        ClientError::from_rpc(err, |handler_error| match handler_error {
+            RpcQueryError::ContractExecutionError { vm_error, .. } => {
+                ClientError::ContractPanic(vm_error)
+            }
+            other => ClientError::RpcHandler(other.to_string()),
+        })
+    }
+}
+
+impl From<JsonRpcError<RpcBlockError>> for ClientError {
+    fn from(err: JsonRpcError<RpcBlockError>) -> Self {
+        ClientError::from_rpc(err, |handler_error| {
+            ClientError::RpcHandler(handler_error.to_string())
+        })
+    }
+}
+
+impl From<JsonRpcError<RpcChunkError>> for ClientError {
+    fn from(err: JsonRpcError<RpcChunkError>) -> Self {
+        ClientError::from_rpc(err, |handler_error| {
+            ClientError::RpcHandler(handler_error.to_string())
+        })
+    }
+}
@@ -0,0 +1,129 @@
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::constants::JOURNAL_FILE;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum TxStatus {
+    /// Signed and about to be broadcast
+    Pending,
+    /// Reached the requested wait level but is not final yet
+    Sent,
+    Final,
+    Failed,
+    /// Broadcast, but the outcome could not be confirmed
+    Unconfirmed,
+}
+
+/// Who sent a transaction and why, for the journal.
+#[derive(Clone, Default)]
+pub struct TxContext {
+    pub role: Option<String>,
+    pub request_id: Option<String>,
+}
+
+impl TxContext {
+    pub fn new(role: &str, request_id: &str) -> Self {
+        Self {
+            role: Some(role.to_string()),
+            request_id: Some(request_id.to_string()),
+        }
+    }
+
+    pub fn role(role: &str) -> Self {
+        Self {
+            role: Some(role.to_string()),
+            request_id: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct JournalRecord {
+    /// Unix time in seconds
+    pub timestamp: u64,
+    pub tx_hash: String,
+    pub signer_id: String,
+    pub nonce: u64,
+    pub method: String,
+    pub request_id: Option<String>,
+    pub role: Option<String>,
+    pub status: TxStatus,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub logs: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Append-only JSON Lines log of every broadcast transaction, in the data directory.
+///
+/// A transaction gets a `pending` record before it is sent and another record each time more
+/// of its outcome is known; the last record for a hash is its current state.
+pub struct Journal {
+    path: PathBuf,
+    file: Mutex<File>,
+}
+
+impl Journal {
+    pub fn open(data_dir: &Path) -> std::io::Result<Self> {
+        fs::create_dir_all(data_dir)?;
+        let path = data_dir.join(JOURNAL_FILE);
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+
+        Ok(Self {
+            path,
+            file: Mutex::new(file),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Appends `record` and syncs it to disk, so it survives a crash right after the broadcast.
+    pub fn append(&self, record: &JournalRecord) -> std::io::Result<()> {
+        let mut line = serde_json::to_vec(record)?;
+        line.push(b'\n');
+
+        let mut file = self.file.lock().unwrap();
+        file.write_all(&line)?;
+        file.sync_data()
+    }
+}
+
+/// Reads every record of the journal in `data_dir`. A line cut short by a crash is skipped.
+pub fn read_journal(data_dir: &Path) -> Result<Vec<JournalRecord>, Box<dyn std::error::Error>> {
+    let path = data_dir.join(JOURNAL_FILE);
+    let file = match File::open(&path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err.into()),
+    };
+
+    let mut records = Vec::new();
+    for line in BufReader::new(file).lines() {
+        let line = line?;
+        match serde_json::from_str(&line) {
+            Ok(record) => records.push(record),
+            Err(err) => eprintln!(
+                "Skipping unreadable journal line in {}: {}",
+                path.display(),
+                err
+            ),
+        }
+    }
+
+    Ok(records)
+}
+
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default()
+}
@@ -0,0 +1,85 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::sync::{Semaphore, SemaphorePermit};
+
+use crate::nonce_manager::NonceManager;
+use crate::rpc_pool::RpcPool;
+use crate::signer::TxSigner;
+
+/// Access keys of one account, each with its own nonce sequence, so that several
+/// transactions can be in flight at once without racing on a nonce.
+pub struct AccessKeyPool {
+    keys: Vec<PooledKey>,
+    free: Semaphore,
+}
+
+struct PooledKey {
+    signer: Arc<dyn TxSigner>,
+    nonce_manager: NonceManager,
+    in_use: AtomicBool,
+}
+
+/// Exclusive use of one key of the pool until dropped.
+pub struct KeyLease<'a> {
+    key: &'a PooledKey,
+    _permit: SemaphorePermit<'a>,
+}
+
+impl AccessKeyPool {
+    pub fn new(client: Arc<RpcPool>, signers: Vec<Arc<dyn TxSigner>>) -> Self {
+        assert!(!signers.is_empty(), "AccessKeyPool needs at least one key");
+
+        let keys: Vec<PooledKey> = signers
+            .into_iter()
+            .map(|signer| PooledKey {
+                nonce_manager: NonceManager::new(client.clone(), signer.clone()),
+                signer,
+                in_use: AtomicBool::new(false),
+            })
+            .collect();
+        let free = Semaphore::new(keys.len());
+
+        Self { keys, free }
+    }
+
+    /// Waits until a key is free and reserves it.
+    pub async fn acquire(&self) -> KeyLease<'_> {
+        let permit = self
+            .free
+            .acquire()
+            .await
+            .expect("the pool semaphore is never closed");
+
+        // Holding a permit guarantees at least one key is not in use
+        let key = self
+            .keys
+            .iter()
+            .find(|key| {
+                key.in_use
+                    .compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
+                    .is_ok()
+            })
+            .expect("a permit is held, so a key is free");
+
+        KeyLease {
+            key,
+            _permit: permit,
+        }
+    }
+}
+
+impl KeyLease<'_> {
+    pub fn signer(&self) -> &dyn TxSigner {
+        self.key.signer.as_ref()
+    }
+
+    pub fn nonce_manager(&self) -> &NonceManager {
+        &self.key.nonce_manager
+    }
+}
+
+impl Drop for KeyLease<'_> {
+    fn drop(&mut self) {
+        self.key.in_use.store(false, Ordering::Release);
+    }
+}
@@ -0,0 +1,223 @@
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use near_crypto::{PublicKey, SecretKey};
+use near_sdk::AccountId;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::constants::{KEYSTORE_DIR, SCRYPT_LOG_N, SCRYPT_P, SCRYPT_R};
+
+const KDF: &str = "scrypt";
+const CIPHER: &str = "xchacha20poly1305";
+
+/// On-disk format of one keystore entry. Only `ciphertext` is secret; the public key is kept in
+/// the clear so it can be listed without the passphrase.
+#[derive(Serialize, Deserialize)]
+pub struct EncryptedKey {
+    pub account_id: AccountId,
+    pub public_key: PublicKey,
+    kdf: String,
+    log_n: u8,
+    r: u32,
+    p: u32,
+    salt: String,
+    cipher: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+pub struct Keystore {
+    dir: PathBuf,
+}
+
+impl Keystore {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    /// `--keystore-dir` if given, otherwise `~/.earthmind/keystore`.
+    pub fn open(dir: Option<&Path>) -> Result<Self, Box<dyn std::error::Error>> {
+        let dir = match dir {
+            Some(dir) => dir.to_path_buf(),
+            None => std::env::var_os("HOME")
+                .map(|home| PathBuf::from(home).join(KEYSTORE_DIR))
+                .ok_or("HOME is not set, pass --keystore-dir")?,
+        };
+
+        Ok(Self::new(dir))
+    }
+
+    pub fn contains(&self, account_id: &AccountId) -> bool {
+        self.path(account_id).exists()
+    }
+
+    pub fn import(
+        &self,
+        account_id: &AccountId,
+        secret_key: &SecretKey,
+        passphrase: &str,
+    ) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        let mut salt = [0u8; 16];
+        OsRng.fill_bytes(&mut salt);
+        let key = derive_key(passphrase, &salt, SCRYPT_LOG_N, SCRYPT_R, SCRYPT_P)?;
+
+        let cipher = XChaCha20Poly1305::new(Key::from_slice(&key));
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let plaintext = secret_key.to_string();
+        let ciphertext = cipher
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: plaintext.as_bytes(),
+                    aad: account_id.as_str().as_bytes(),
+                },
+            )
+            .map_err(|_| "Failed to encrypt secret key")?;
+
+        let entry = EncryptedKey {
+            account_id: account_id.clone(),
+            public_key: secret_key.public_key(),
+            kdf: KDF.to_string(),
+            log_n: SCRYPT_LOG_N,
+            r: SCRYPT_R,
+            p: SCRYPT_P,
+            salt: hex::encode(salt),
+            cipher: CIPHER.to_string(),
+            nonce: hex::encode(nonce),
+            ciphertext: hex::encode(ciphertext),
+        };
+
+        fs::create_dir_all(&self.dir)?;
+        let path = self.path(account_id);
+        fs::write(&path, serde_json::to_vec_pretty(&entry)?)?;
+        restrict_permissions(&path)?;
+
+        Ok(path)
+    }
+
+    pub fn unlock(
+        &self,
+        account_id: &AccountId,
+        passphrase: &str,
+    ) -> Result<SecretKey, Box<dyn std::error::Error>> {
+        let entry = self.read(account_id)?;
+
+        if entry.kdf != KDF || entry.cipher != CIPHER {
+            return Err(format!(
+                "Unsupported keystore entry for {} ({}/{})",
+                account_id, entry.kdf, entry.cipher
+            )
+            .into());
+        }
+
+        let salt = hex::decode(&entry.salt)?;
+        let nonce = hex::decode(&entry.nonce)?;
+        let ciphertext = hex::decode(&entry.ciphertext)?;
+        if nonce.len() != 24 {
+            return Err(format!("Corrupted keystore entry for {}", account_id).into());
+        }
+
+        let key = derive_key(passphrase, &salt, entry.log_n, entry.r, entry.p)?;
+        let cipher = XChaCha20Poly1305::new(Key::from_slice(&key));
+        let plaintext = cipher
+            .decrypt(
+                XNonce::from_slice(&nonce),
+                Payload {
+                    msg: &ciphertext,
+                    aad: account_id.as_str().as_bytes(),
+                },
+            )
+            .map_err(|_| format!("Wrong passphrase for {}", account_id))?;
+
+        let secret_key: SecretKey = String::from_utf8(plaintext)?.parse()?;
+        if secret_key.public_key() != entry.public_key {
+            return Err(format!(
+                "Keystore entry for {} does not match its public key",
+                account_id
+            )
+            .into());
+        }
+
+        Ok(secret_key)
+    }
+
+    pub fn read(&self, account_id: &AccountId) -> Result<EncryptedKey, Box<dyn std::error::Error>> {
+        let path = self.path(account_id);
+        let contents = fs::read(&path)
+            .map_err(|err| format!("Failed to read keystore entry {}: {}", path.display(), err))?;
+
+        Ok(serde_json::from_slice(&contents)?)
+    }
+
+    pub fn list(&self) -> Result<Vec<EncryptedKey>, Box<dyn std::error::Error>> {
+        let mut entries = Vec::new();
+
+        if !self.dir.exists() {
+            return Ok(entries);
+        }
+
+        for dir_entry in fs::read_dir(&self.dir)? {
+            let path = dir_entry?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+                continue;
+            }
+
+            match serde_json::from_slice::<EncryptedKey>(&fs::read(&path)?) {
+                Ok(entry) => entries.push(entry),
+                Err(err) => eprintln!("Skipping {}: {}", path.display(), err),
+            }
+        }
+
+        entries.sort_by(|a, b| a.account_id.cmp(&b.account_id));
+        Ok(entries)
+    }
+
+    fn path(&self, account_id: &AccountId) -> PathBuf {
+        self.dir.join(format!("{}.json", account_id))
+    }
+}
+
+/// Reads the passphrase from `passphrase_file` (first line) or prompts for it on the terminal.
+pub fn read_passphrase(
+    passphrase_file: Option<&Path>,
+    prompt: &str,
+) -> Result<String, Box<dyn std::error::Error>> {
+    match passphrase_file {
+        Some(path) => {
+            let contents = fs::read_to_string(path).map_err(|err| {
+                format!("Failed to read passphrase file {}: {}", path.display(), err)
+            })?;
+            Ok(contents.lines().next().unwrap_or_default().to_string())
+        }
+        None => Ok(rpassword::prompt_password(prompt)?),
+    }
+}
+
+fn derive_key(
+    passphrase: &str,
+    salt: &[u8],
+    log_n: u8,
+    r: u32,
+    p: u32,
+) -> Result<[u8; 32], Box<dyn std::error::Error>> {
+    let params = scrypt::Params::new(log_n, r, p, 32)
+        .map_err(|err| format!("Invalid scrypt parameters: {}", err))?;
+    let mut key = [0u8; 32];
+    scrypt::scrypt(passphrase.as_bytes(), salt, &params, &mut key)
+        .map_err(|err| format!("Failed to derive key: {}", err))?;
+
+    Ok(key)
+}
+
+#[cfg(unix)]
+fn restrict_permissions(path: &Path) -> std::io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o600))
+}
+
+#[cfg(not(unix))]
+fn restrict_permissions(_path: &Path) -> std::io::Result<()> {
+    Ok(())
+}
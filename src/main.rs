@@ -1,92 +1,279 @@
 use clap::Parser;
-use near_crypto::InMemorySigner;
-use near_event_listener::NearEventListener;
-use near_jsonrpc_client::JsonRpcClient;
 use std::sync::Arc;
-use tokio::sync::Mutex;
 
+mod block_listener;
+mod checkpoint;
 mod cli;
+mod commands;
+mod config;
 mod constants;
+mod credentials;
+mod error;
+mod journal;
+mod key_pool;
+mod keystore;
 mod nonce_manager;
+mod offline;
 mod processors;
 mod qx_builder;
 mod qx_sender;
+mod relayer;
+mod rpc_pool;
+mod signer;
+#[cfg(test)]
+mod test_server;
 mod tx_builder;
 mod tx_sender;
 
-use cli::{Cli, Modes, Networks};
-use constants::{
-    ACCOUNT_TO_LISTEN, DEFAULT_TIMEOUT, FUNCTION_TO_LISTEN, NEAR_RPC_MAINNET,
-    NEAR_RPC_TESTNET,
-};
-use nonce_manager::NonceManager;
-use processors::{Aggregator, Miner, TransactionProcessor, Validator};
-use tx_builder::TxBuilder;
+use block_listener::BlockListener;
+use checkpoint::Checkpoint;
+use cli::{Cli, Command, Modes};
+use config::Config;
+use constants::BLOCK_POLL_INTERVAL;
+use journal::Journal;
+use key_pool::AccessKeyPool;
+use processors::{Aggregator, Miner, MinerFleet, TransactionProcessor, Validator};
+use rpc_pool::RpcPool;
+use signer::{TxSigner, WatchOnlySigner};
 use tx_sender::TxSender;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let cli = Cli::parse();
 
-    let rpc_url = match cli.network {
-        Networks::Testnet => NEAR_RPC_TESTNET,
-        Networks::Mainnet => NEAR_RPC_MAINNET,
-    };
+    if let Some(Command::Keys(command)) = &cli.command {
+        return commands::run_keys_command(command, &cli);
+    }
+
+    let config = Arc::new(cli.load_config()?);
+
+    if let Some(Command::Sign { input, out }) = &cli.command {
+        return commands::run_sign_command(input, out.as_deref(), &cli, &config).await;
+    }
+
+    if let Some(Command::History {
+        request_id,
+        role,
+        status,
+        limit,
+    }) = &cli.command
+    {
+        return commands::run_history_command(
+            &config.data_dir,
+            request_id.as_deref(),
+            role.as_deref(),
+            *status,
+            *limit,
+        );
+    }
 
     //initialize client
-    let client = Arc::new(JsonRpcClient::connect(rpc_url));
+    let client = Arc::new(RpcPool::new(&config.rpc_urls(), config.timeout));
 
-    // Create signer
-    let signer = InMemorySigner::from_secret_key(cli.account_id.clone(), cli.private_key.clone());
+    if let Some(Command::Stage { start_time }) = &cli.command {
+        return commands::run_stage_command(client, &config, *start_time)
+            .await
+            .map_err(|e| e.to_string().into());
+    }
 
-    // Initialize components
-    let nonce_manager = Arc::new(NonceManager::new(client.clone(), Arc::new(signer.clone())));
-    let tx_builder = Arc::new(Mutex::new(TxBuilder::new(signer, cli.network)));
-    let tx_sender = Arc::new(TxSender::new(client.clone(), DEFAULT_TIMEOUT));
+    if cli.dry_run {
+        println!("Dry run: transactions are signed and printed, never broadcast");
+    }
+    let journal = Arc::new(Journal::open(&config.data_dir)?);
+    let tx_sender = Arc::new(
+        TxSender::new(client.clone(), config.retry_policy())
+            .with_dry_run(cli.dry_run)
+            .with_export_dir(cli.export_unsigned.clone())
+            .with_journal(journal)
+            .with_relayer(config.relayer_url.as_deref()),
+    );
 
-    // Create the processor based on the mode
-    let processor: Arc<dyn TransactionProcessor> = match cli.mode {
-        Modes::Miner => Arc::new(Miner::new(
-            nonce_manager.clone(),
-            tx_builder.clone(),
-            tx_sender.clone(),
-            cli.account_id.clone(),
-        )),
-        Modes::Validator => Arc::new(Validator::new(
-            nonce_manager.clone(),
-            tx_builder.clone(),
-            tx_sender.clone(),
-            cli.account_id.clone(),
-        )),
-        Modes::Aggregator => Arc::new(Aggregator::new(
-            nonce_manager.clone(),
-            tx_builder.clone(),
+    if let Some(Command::Broadcast { input }) = &cli.command {
+        return commands::run_broadcast_command(input, &tx_sender, &client, &config)
+            .await
+            .map_err(|e| e.to_string().into());
+    }
+
+    if let Some(command) = &cli.command {
+        let account_id = cli.account_id.clone().ok_or("--account-id is required")?;
+        let signer: Arc<dyn TxSigner> = match &cli.public_key {
+            Some(public_key) => {
+                Arc::new(WatchOnlySigner::new(account_id.clone(), public_key.clone()))
+            }
+            None => {
+                credentials::resolve_signer(&cli, &account_id, || {
+                    credentials::load_signer(&cli, &account_id, config.network)
+                })
+                .await?
+            }
+        };
+        let key_pool = build_key_pool(signer, client.clone(), &config)?;
+
+        return commands::run_operator_command(command, key_pool, tx_sender, account_id, config)
+            .await
+            .map_err(|e| e.to_string().into());
+    }
+
+    if cli.export_unsigned.is_some() {
+        return Err("--export-unsigned is only supported with a single transaction command".into());
+    }
+    let account_to_listen = config.account_to_listen()?.clone();
+
+    // Create one processor per configured role, falling back to --mode/--account-id
+    let mut processors: Vec<Arc<dyn TransactionProcessor>> = Vec::new();
+    if !config.roles.is_empty() {
+        for role in &config.roles {
+            let signer = credentials::resolve_signer(&cli, &role.account_id, || {
+                credentials::load_account_signer(
+                    &cli,
+                    &role.account_id,
+                    role.key_file.as_deref(),
+                    config.network,
+                )
+            })
+            .await?;
+            processors.push(build_processor(
+                role.mode,
+                signer,
+                client.clone(),
+                tx_sender.clone(),
+                config.clone(),
+            )?);
+        }
+    } else if let Some(mode) = cli.mode {
+        let account_id = cli
+            .account_id
+            .clone()
+            .ok_or("--account-id is required to start the listener")?;
+        let signer = credentials::resolve_signer(&cli, &account_id, || {
+            credentials::load_signer(&cli, &account_id, config.network)
+        })
+        .await?;
+        processors.push(build_processor(
+            mode,
+            signer,
+            client.clone(),
             tx_sender.clone(),
-            cli.account_id,
-        )),
+            config.clone(),
+        )?);
+    } else if config.fleet.is_empty() {
+        return Err(
+            "--mode, --role or a [[fleet]] config is required to start the listener".into(),
+        );
+    }
+
+    if !config.fleet.is_empty() {
+        let mut miners = Vec::new();
+        for fleet_miner in &config.fleet {
+            let signer = credentials::resolve_signer(&cli, &fleet_miner.account_id, || {
+                credentials::load_account_signer(
+                    &cli,
+                    &fleet_miner.account_id,
+                    fleet_miner.key_file.as_deref(),
+                    config.network,
+                )
+            })
+            .await?;
+            let key_pool = build_key_pool(signer, client.clone(), &config)?;
+            miners.push(Arc::new(
+                Miner::new(
+                    key_pool,
+                    tx_sender.clone(),
+                    fleet_miner.account_id.clone(),
+                    config.clone(),
+                )
+                .with_answer_strategy(fleet_miner.answer),
+            ));
+        }
+        processors.push(Arc::new(MinerFleet::new(miners, config.clone())));
+    }
+
+    // Resolve the block to resume from
+    let checkpoint = Arc::new(Checkpoint::new(&config.data_dir));
+    let saved_block = checkpoint.load().map_err(|e| e.to_string())?;
+    let start_block = match (cli.from_block, saved_block) {
+        (Some(height), _) => height,
+        (None, Some(height)) if !cli.from_latest => height + 1,
+        _ => checkpoint::latest_final_height(&client).await?,
     };
+    println!("Starting from block: {}", start_block);
+
+    tokio::spawn(checkpoint.clone().run(config.checkpoint_interval));
+    tokio::spawn(
+        client
+            .clone()
+            .run_health_checks(config.health_check_interval),
+    );
 
-    let mut listener = NearEventListener::builder(rpc_url)
-        .account_id(ACCOUNT_TO_LISTEN)
-        .method_name(FUNCTION_TO_LISTEN)
-        .last_processed_block(181088453)
-        .build()?;
+    let listener = BlockListener::new(
+        client.clone(),
+        account_to_listen,
+        config.function_to_listen.clone(),
+        BLOCK_POLL_INTERVAL,
+    );
 
     listener
-        .start(move |event_log| {
-            println!("Standard: {}", event_log.standard);
-            println!("Version: {}", event_log.version);
-            println!("Event: {}", event_log.event);
-            println!("Data: {}", event_log.data);
-
-            let processor = processor.clone();
-            tokio::spawn(async move {
-                if let Err(e) = processor.process_transaction(event_log).await {
-                    eprintln!("Error processing transaction: {}", e);
+        .run(start_block, |height, events| {
+            for event_log in events {
+                println!("Standard: {}", event_log.standard);
+                println!("Version: {}", event_log.version);
+                println!("Event: {}", event_log.event);
+                println!("Data: {}", event_log.data);
+
+                for processor in &processors {
+                    let processor = processor.clone();
+                    let checkpoint = checkpoint.clone();
+                    let event_log = event_log.clone();
+                    checkpoint.begin_event(height);
+                    tokio::spawn(async move {
+                        if let Err(e) = processor.process_transaction(event_log).await {
+                            eprintln!("Error processing transaction: {}", e);
+                        }
+                        checkpoint.finish_event(height);
+                    });
                 }
-            });
+            }
+            checkpoint.finish_block(height);
         })
-        .await?;
+        .await;
 
     Ok(())
 }
+
+fn build_processor(
+    mode: Modes,
+    signer: Arc<dyn TxSigner>,
+    client: Arc<RpcPool>,
+    tx_sender: Arc<TxSender>,
+    config: Arc<Config>,
+) -> Result<Arc<dyn TransactionProcessor>, Box<dyn std::error::Error>> {
+    let account_id = signer.account_id().clone();
+    let key_pool = build_key_pool(signer, client, &config)?;
+
+    Ok(match mode {
+        Modes::Miner => Arc::new(Miner::new(key_pool, tx_sender, account_id, config)),
+        Modes::Validator => Arc::new(Validator::new(key_pool, tx_sender, account_id, config)),
+        Modes::Aggregator => Arc::new(Aggregator::new(key_pool, tx_sender, account_id, config)),
+    })
+}
+
+/// Key pool of the signer's account, with any extra access keys configured for it.
+fn build_key_pool(
+    signer: Arc<dyn TxSigner>,
+    client: Arc<RpcPool>,
+    config: &Config,
+) -> Result<Arc<AccessKeyPool>, Box<dyn std::error::Error>> {
+    let mut signers = vec![signer];
+    for extra in credentials::load_access_keys(signers[0].account_id(), &config.access_key_files)? {
+        if signers.iter().all(|s| s.public_key() != &extra.public_key) {
+            signers.push(Arc::new(extra));
+        }
+    }
+    println!(
+        "Signing for {} with {} access key(s)",
+        signers[0].account_id(),
+        signers.len()
+    );
+
+    Ok(Arc::new(AccessKeyPool::new(client, signers)))
+}
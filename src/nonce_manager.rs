@@ -1,51 +1,131 @@
-use near_crypto::InMemorySigner;
 use near_jsonrpc_client::methods;
-use near_jsonrpc_client::JsonRpcClient;
 use near_jsonrpc_primitives::types::query::QueryResponseKind;
 use near_primitives::hash::CryptoHash;
-use near_primitives::types::BlockReference;
+use near_primitives::types::{BlockHeight, BlockReference, Finality};
 use near_primitives::views::QueryRequest;
 use std::sync::Arc;
+use std::time::Instant;
 use tokio::sync::Mutex;
 
+use crate::constants::{BLOCK_HASH_MAX_AGE, NONCE_RESYNC_INTERVAL};
+use crate::error::ClientError;
+use crate::rpc_pool::RpcPool;
+use crate::signer::TxSigner;
+
+/// Hands out nonces and recent block hashes for one access key.
+///
+/// The nonce is read from the access key once and then counted locally. It is read again
+/// every `NONCE_RESYNC_INTERVAL`, and corrected through `resync` when the node rejects a nonce.
+/// The block hash is reused until it is `BLOCK_HASH_MAX_AGE` old.
 pub struct NonceManager {
-    client: Arc<JsonRpcClient>,
-    signer: Arc<InMemorySigner>,
-    current_nonce: Mutex<u64>,
+    client: Arc<RpcPool>,
+    signer: Arc<dyn TxSigner>,
+    state: Mutex<NonceState>,
+}
+
+#[derive(Default)]
+struct NonceState {
+    current_nonce: u64,
+    synced_at: Option<Instant>,
+    block: Option<(CryptoHash, Instant)>,
 }
 
 impl NonceManager {
-    pub fn new(client: Arc<JsonRpcClient>, signer: Arc<InMemorySigner>) -> Self {
+    pub fn new(client: Arc<RpcPool>, signer: Arc<dyn TxSigner>) -> Self {
         Self {
             client,
             signer,
-            current_nonce: Mutex::new(0),
+            state: Mutex::new(NonceState::default()),
+        }
+    }
+
+    pub async fn get_nonce_and_tx_hash(&self) -> Result<(u64, CryptoHash), ClientError> {
+        self.next_nonce().await
+    }
+
+    /// Nonce and the latest final height for a delegate action, which expires a fixed number of
+    /// blocks after that height. The height is always fetched, as a cached one would eat into
+    /// the delegate action's short validity.
+    pub async fn get_nonce_and_block_height(&self) -> Result<(u64, BlockHeight), ClientError> {
+        let (_, block_height) = self.latest_block().await?;
+        let (nonce, _) = self.next_nonce().await?;
+        Ok((nonce, block_height))
+    }
+
+    async fn next_nonce(&self) -> Result<(u64, CryptoHash), ClientError> {
+        let mut state = self.state.lock().await;
+
+        let needs_sync =
+            !matches!(state.synced_at, Some(at) if at.elapsed() < NONCE_RESYNC_INTERVAL);
+        if needs_sync {
+            self.sync(&mut state).await?;
         }
+
+        let block_hash = match state.block {
+            Some((hash, fetched_at)) if fetched_at.elapsed() < BLOCK_HASH_MAX_AGE => hash,
+            _ => {
+                let (hash, _) = self.latest_block().await?;
+                state.block = Some((hash, Instant::now()));
+                hash
+            }
+        };
+
+        state.current_nonce += 1;
+        println!("Using nonce: {}", state.current_nonce);
+        Ok((state.current_nonce, block_hash))
+    }
+
+    /// Moves the local counter past the nonce the node reported when it rejected a transaction
+    /// and returns the nonce to re-sign with.
+    pub async fn resync(&self, ak_nonce: u64) -> u64 {
+        let mut state = self.state.lock().await;
+        let new_nonce = std::cmp::max(ak_nonce, state.current_nonce) + 1;
+        state.current_nonce = new_nonce;
+        state.synced_at = Some(Instant::now());
+        println!("Resynced nonce: {}", new_nonce);
+        new_nonce
+    }
+
+    /// Drops the cached block hash after the node reported it as expired.
+    pub async fn expire_block_hash(&self) {
+        self.state.lock().await.block = None;
     }
 
-    pub async fn get_nonce_and_tx_hash(
-        &self,
-    ) -> Result<(u64, CryptoHash), Box<dyn std::error::Error + Send + Sync>> {
+    /// Reads the access key nonce, which also yields a fresh block hash.
+    async fn sync(&self, state: &mut NonceState) -> Result<(), ClientError> {
         let access_key_query_response = self
             .client
             .call(methods::query::RpcQueryRequest {
                 block_reference: BlockReference::latest(),
                 request: QueryRequest::ViewAccessKey {
-                    account_id: self.signer.account_id.clone(),
-                    public_key: self.signer.public_key.clone(),
+                    account_id: self.signer.account_id().clone(),
+                    public_key: self.signer.public_key().clone(),
                 },
             })
             .await?;
 
         match access_key_query_response.kind {
             QueryResponseKind::AccessKey(access_key) => {
-                let mut current_nonce = self.current_nonce.lock().await;
-                let new_nonce = std::cmp::max(access_key.nonce, *current_nonce) + 1;
-                *current_nonce = new_nonce;
-                println!("Using nonce: {}", new_nonce);
-                Ok((new_nonce, access_key_query_response.block_hash))
+                // Nonces handed out since the last sync may not have landed yet
+                state.current_nonce = std::cmp::max(access_key.nonce, state.current_nonce);
+                state.synced_at = Some(Instant::now());
+                state.block = Some((access_key_query_response.block_hash, Instant::now()));
+                Ok(())
             }
-            _ => Err("Failed to extract current nonce".into()),
+            _ => Err(ClientError::UnexpectedResponse(
+                "Failed to extract current nonce".to_string(),
+            )),
         }
     }
+
+    async fn latest_block(&self) -> Result<(CryptoHash, BlockHeight), ClientError> {
+        let block = self
+            .client
+            .call(methods::block::RpcBlockRequest {
+                block_reference: BlockReference::Finality(Finality::Final),
+            })
+            .await?;
+
+        Ok((block.header.hash, block.header.height))
+    }
 }
@@ -0,0 +1,144 @@
+use near_primitives::action::Action;
+use near_primitives::borsh;
+use near_primitives::serialize::{from_base64, to_base64};
+use near_primitives::transaction::{SignedTransaction, Transaction};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// JSON file carrying a transaction to an offline signer. Everything but `transaction` is
+/// there for the person reviewing it; the borsh bytes are what gets signed.
+#[derive(Serialize, Deserialize)]
+pub struct UnsignedTransactionFile {
+    pub hash: String,
+    pub signer_id: String,
+    pub public_key: String,
+    pub receiver_id: String,
+    pub nonce: u64,
+    pub block_hash: String,
+    pub actions: Vec<String>,
+    /// Base64 borsh encoding of the `Transaction`
+    pub transaction: String,
+}
+
+/// JSON file carrying a signed transaction back to the broadcaster.
+#[derive(Serialize, Deserialize)]
+pub struct SignedTransactionFile {
+    pub hash: String,
+    /// Base64 borsh encoding of the `SignedTransaction`
+    pub signed_transaction: String,
+}
+
+/// Writes `transaction` to `<dir>/<hash>.unsigned.json`.
+pub fn write_unsigned(
+    dir: &Path,
+    transaction: &Transaction,
+) -> Result<PathBuf, Box<dyn std::error::Error + Send + Sync>> {
+    let file = unsigned_file(transaction)?;
+
+    fs::create_dir_all(dir)?;
+    let path = dir.join(format!("{}.unsigned.json", file.hash));
+    fs::write(&path, serde_json::to_string_pretty(&file)?)?;
+
+    Ok(path)
+}
+
+/// Reads a transaction exported by `write_unsigned`, rejecting the file if any of its readable
+/// fields differs from the encoded transaction.
+pub fn read_unsigned(path: &Path) -> Result<Transaction, Box<dyn std::error::Error>> {
+    let file: UnsignedTransactionFile = serde_json::from_str(&fs::read_to_string(path)?)?;
+    let transaction: Transaction = borsh::from_slice(&from_base64(&file.transaction)?)?;
+
+    // The readable fields must describe the bytes that will be signed
+    let expected = unsigned_file(&transaction)?;
+    let mismatched: Vec<&str> = [
+        ("hash", file.hash == expected.hash),
+        ("signer_id", file.signer_id == expected.signer_id),
+        ("public_key", file.public_key == expected.public_key),
+        ("receiver_id", file.receiver_id == expected.receiver_id),
+        ("nonce", file.nonce == expected.nonce),
+        ("block_hash", file.block_hash == expected.block_hash),
+        ("actions", file.actions == expected.actions),
+    ]
+    .into_iter()
+    .filter(|(_, matches)| !matches)
+    .map(|(field, _)| field)
+    .collect();
+    if !mismatched.is_empty() {
+        return Err(format!(
+            "{}: {} do not match the encoded transaction",
+            path.display(),
+            mismatched.join(", ")
+        )
+        .into());
+    }
+
+    Ok(transaction)
+}
+
+fn unsigned_file(transaction: &Transaction) -> std::io::Result<UnsignedTransactionFile> {
+    Ok(UnsignedTransactionFile {
+        hash: transaction.get_hash_and_size().0.to_string(),
+        signer_id: transaction.signer_id.to_string(),
+        public_key: transaction.public_key.to_string(),
+        receiver_id: transaction.receiver_id.to_string(),
+        nonce: transaction.nonce,
+        block_hash: transaction.block_hash.to_string(),
+        actions: transaction.actions.iter().map(describe_action).collect(),
+        transaction: to_base64(&borsh::to_vec(transaction)?),
+    })
+}
+
+/// Prints the signer, receiver, nonce, block hash and actions of `transaction`, indented under
+/// a heading printed by the caller.
+pub fn print_details(transaction: &Transaction) {
+    println!(
+        "  signer {}, receiver {}, nonce {}, block hash {}",
+        transaction.signer_id, transaction.receiver_id, transaction.nonce, transaction.block_hash
+    );
+    for action in &transaction.actions {
+        println!("  {}", describe_action(action));
+    }
+}
+
+/// One line per action for the person reviewing a transaction. Function call arguments are
+/// shown in full, as `Debug` abbreviates them.
+fn describe_action(action: &Action) -> String {
+    match action {
+        Action::FunctionCall(call) => format!(
+            "call {} {} (gas {}, deposit {})",
+            call.method_name,
+            String::from_utf8_lossy(&call.args),
+            call.gas,
+            call.deposit
+        ),
+        other => format!("{:?}", other),
+    }
+}
+
+pub fn write_signed(
+    path: &Path,
+    signed_transaction: &SignedTransaction,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let file = SignedTransactionFile {
+        hash: signed_transaction.get_hash().to_string(),
+        signed_transaction: to_base64(&borsh::to_vec(signed_transaction)?),
+    };
+    fs::write(path, serde_json::to_string_pretty(&file)?)?;
+
+    Ok(())
+}
+
+pub fn read_signed(
+    path: &Path,
+) -> Result<SignedTransaction, Box<dyn std::error::Error + Send + Sync>> {
+    let file: SignedTransactionFile = serde_json::from_str(&fs::read_to_string(path)?)?;
+    let signed_transaction: SignedTransaction =
+        borsh::from_slice(&from_base64(&file.signed_transaction)?)?;
+
+    if signed_transaction.get_hash().to_string() != file.hash {
+        return Err(format!("{} does not match its hash", path.display()).into());
+    }
+
+    Ok(signed_transaction)
+}
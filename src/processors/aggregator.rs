@@ -1,51 +1,53 @@
-use crate::nonce_manager::NonceManager;
+use crate::config::Config;
+use crate::error::ClientError;
+use crate::journal::TxContext;
+use crate::key_pool::AccessKeyPool;
 use crate::tx_builder::TxBuilder;
 use crate::tx_sender::TxSender;
 use near_event_listener::EventLog;
 
 use async_trait::async_trait;
-use near_jsonrpc_client::methods;
-use near_primitives::views::TxExecutionStatus;
 use near_sdk::AccountId;
 use std::sync::Arc;
-use tokio::sync::Mutex;
-use tokio::time::{sleep, Duration};
+use tokio::time::sleep;
 
+use super::transaction_processor::event_str;
 use super::TransactionProcessor;
 
 pub struct Aggregator {
-    nonce_manager: Arc<NonceManager>,
-    tx_builder: Arc<Mutex<TxBuilder>>,
+    key_pool: Arc<AccessKeyPool>,
     tx_sender: Arc<TxSender>,
     account_id: AccountId,
+    config: Arc<Config>,
 }
 
 impl Aggregator {
     pub fn new(
-        nonce_manager: Arc<NonceManager>,
-        tx_builder: Arc<Mutex<TxBuilder>>,
+        key_pool: Arc<AccessKeyPool>,
         tx_sender: Arc<TxSender>,
         account_id: AccountId,
+        config: Arc<Config>,
     ) -> Self {
         Self {
-            nonce_manager,
-            tx_builder,
+            key_pool,
             tx_sender,
             account_id,
+            config,
         }
     }
 }
 
 #[async_trait]
 impl TransactionProcessor for Aggregator {
-    async fn process_transaction(
-        &self,
-        event_data: EventLog,
-    ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+    fn config(&self) -> &Config {
+        &self.config
+    }
+
+    async fn process_transaction(&self, event_data: EventLog) -> Result<bool, ClientError> {
         println!("Aggregator Processor");
         println!("Event Data: {:?}", event_data);
 
-        let aggregator_attempts = 30;
+        let aggregator_attempts = self.config.stage_attempts;
 
         for _attempt in 0..aggregator_attempts {
             // Get stage to synchronize
@@ -56,10 +58,14 @@ impl TransactionProcessor for Aggregator {
             println!("Current Stage: {:?}", stage);
 
             if stage == "Ended" {
-                match self::obtain_top_ten(self, event_data).await {
+                match self::obtain_top_ten(self, event_data.clone()).await {
                     Ok(_) => {
                         return Ok(true);
                     }
+                    Err(e) if e.is_retryable() => {
+                        println!("Retrying top ten after: {}", e);
+                        sleep(self.config.poll_interval).await;
+                    }
                     Err(e) => {
                         println!("Failed to obtain top ten voters: {}", e);
                         return Err(e);
@@ -67,7 +73,7 @@ impl TransactionProcessor for Aggregator {
                 }
             } else {
                 println!("Waiting for Ended stage...");
-                sleep(Duration::from_secs(10)).await;
+                sleep(self.config.poll_interval).await;
             }
         }
 
@@ -78,52 +84,50 @@ impl TransactionProcessor for Aggregator {
         Ok(false)
     }
 
-    async fn commit(
-        &self,
-        _event_data: EventLog,
-    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    async fn commit(&self, _event_data: EventLog) -> Result<(), ClientError> {
         Ok(())
     }
 
-    async fn reveal(
-        &self,
-        _event_data: EventLog,
-    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    async fn reveal(&self, _event_data: EventLog) -> Result<(), ClientError> {
         Ok(())
     }
 }
 pub async fn obtain_top_ten(
     aggregator: &Aggregator,
     event_data: EventLog,
-) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+) -> Result<(), ClientError> {
     println!("Obtaining top ten voters");
 
-    let (nonce, block_hash) = aggregator.nonce_manager.get_nonce_and_tx_hash().await?;
-
-    let mut tx_builder = aggregator.tx_builder.lock().await;
-
-    let request_id = event_data.data[0]["request_id"]
-        .as_str()
-        .unwrap_or_default()
-        .to_string();
-
-    let (tx, _) = tx_builder
-        .with_method_name("get_top_10_voters")
-        .with_args(serde_json::json!({
-            "request_id": request_id,
-        }))
-        .build(nonce, block_hash);
-
-    let signer = &tx_builder.signer;
-
-    let request = methods::send_tx::RpcSendTransactionRequest {
-        signed_transaction: tx.sign(signer),
-        wait_until: TxExecutionStatus::Final,
-    };
-
-    let tx_response = aggregator.tx_sender.send_transaction(request).await?;
-    let log_tx = aggregator.extract_logs(&tx_response);
-    println!("TOP_TEN LOG: {:?}", log_tx);
+    let key = aggregator.key_pool.acquire().await;
+    let (nonce, block_hash) = key.nonce_manager().get_nonce_and_tx_hash().await?;
+
+    let request_id = event_str(&event_data, "request_id")?;
+
+    let wait_until = aggregator
+        .config
+        .method_profile("get_top_10_voters")
+        .wait_until;
+    let tx = TxBuilder::new(aggregator.config.protocol_contract.clone())
+        .profiled_call(
+            &aggregator.config,
+            "get_top_10_voters",
+            serde_json::json!({
+                "request_id": request_id,
+            }),
+        )
+        .build(key.signer(), nonce, block_hash);
+
+    let tx_response = aggregator
+        .tx_sender
+        .sign_and_send(
+            tx,
+            wait_until.into(),
+            key.signer(),
+            key.nonce_manager(),
+            &TxContext::new("aggregator", &request_id),
+        )
+        .await?;
+    aggregator.report_logs("TOP_TEN LOG", &request_id, &tx_response);
 
     Ok(())
 }
@@ -0,0 +1,95 @@
+use crate::config::Config;
+use crate::error::ClientError;
+use near_event_listener::EventLog;
+
+use async_trait::async_trait;
+use near_sdk::AccountId;
+use std::sync::Arc;
+use tokio::task::JoinSet;
+
+use super::transaction_processor::event_str;
+use super::{Miner, TransactionProcessor};
+
+/// Drives many miner accounts from the same events and reports how each one did.
+pub struct MinerFleet {
+    miners: Vec<Arc<Miner>>,
+    config: Arc<Config>,
+}
+
+impl MinerFleet {
+    pub fn new(miners: Vec<Arc<Miner>>, config: Arc<Config>) -> Self {
+        Self { miners, config }
+    }
+}
+
+#[async_trait]
+impl TransactionProcessor for MinerFleet {
+    fn config(&self) -> &Config {
+        &self.config
+    }
+
+    async fn process_transaction(&self, event_data: EventLog) -> Result<bool, ClientError> {
+        println!("Miner Fleet Processor ({} miners)", self.miners.len());
+
+        let request_id = event_str(&event_data, "request_id")?;
+
+        let mut tasks = JoinSet::new();
+        for miner in &self.miners {
+            let miner = miner.clone();
+            let event_data = event_data.clone();
+            tasks.spawn(async move {
+                let result = miner.process_transaction(event_data).await;
+                (miner.account_id().clone(), result)
+            });
+        }
+
+        let mut results: Vec<(AccountId, Result<bool, String>)> = Vec::new();
+        while let Some(joined) = tasks.join_next().await {
+            match joined {
+                Ok((account_id, result)) => {
+                    results.push((account_id, result.map_err(|e| e.to_string())))
+                }
+                Err(e) => eprintln!("Fleet miner task panicked: {}", e),
+            }
+        }
+        results.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let participated = results
+            .iter()
+            .filter(|(_, r)| matches!(r, Ok(true)))
+            .count();
+        let skipped = results
+            .iter()
+            .filter(|(_, r)| matches!(r, Ok(false)))
+            .count();
+        let failed = results.iter().filter(|(_, r)| r.is_err()).count();
+
+        println!(
+            "FLEET_REPORT request {}: {} participated, {} skipped, {} failed",
+            request_id, participated, skipped, failed
+        );
+        for (account_id, result) in &results {
+            match result {
+                Ok(true) => println!("  {}: participated", account_id),
+                Ok(false) => println!("  {}: skipped", account_id),
+                Err(e) => println!("  {}: failed: {}", account_id, e),
+            }
+        }
+
+        Ok(participated > 0)
+    }
+
+    async fn commit(&self, event_data: EventLog) -> Result<(), ClientError> {
+        for miner in &self.miners {
+            miner.commit(event_data.clone()).await?;
+        }
+        Ok(())
+    }
+
+    async fn reveal(&self, event_data: EventLog) -> Result<(), ClientError> {
+        for miner in &self.miners {
+            miner.reveal(event_data.clone()).await?;
+        }
+        Ok(())
+    }
+}
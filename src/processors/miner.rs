@@ -1,5 +1,7 @@
-use crate::constants::ACCOUNT_TO_LISTEN;
-use crate::nonce_manager::NonceManager;
+use crate::config::Config;
+use crate::error::ClientError;
+use crate::journal::TxContext;
+use crate::key_pool::AccessKeyPool;
 use crate::qx_builder::QueryBuilder;
 use crate::qx_sender::QuerySender;
 use crate::tx_builder::TxBuilder;
@@ -7,50 +9,85 @@ use crate::tx_sender::TxSender;
 use near_event_listener::EventLog;
 
 use async_trait::async_trait;
-use near_jsonrpc_client::methods;
-use near_primitives::views::TxExecutionStatus;
+use near_primitives::hash::hash;
 use near_sdk::AccountId;
-use tokio::time::{sleep, Duration};
+use serde::Deserialize;
+use tokio::time::sleep;
 
 use std::sync::Arc;
-use tokio::sync::Mutex;
 
+use super::transaction_processor::event_str;
 use super::TransactionProcessor;
 
+/// How a miner answers a governance request.
+#[derive(Copy, Clone, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AnswerStrategy {
+    #[default]
+    Yes,
+    No,
+    /// Picks yes or no per request, derived from the account and request id so that the
+    /// reveal matches the commit even after a restart
+    Random,
+}
+
 pub struct Miner {
-    nonce_manager: Arc<NonceManager>,
-    tx_builder: Arc<Mutex<TxBuilder>>,
+    key_pool: Arc<AccessKeyPool>,
     tx_sender: Arc<TxSender>,
     account_id: AccountId,
+    config: Arc<Config>,
+    answer_strategy: AnswerStrategy,
 }
 
 impl Miner {
     pub fn new(
-        nonce_manager: Arc<NonceManager>,
-        tx_builder: Arc<Mutex<TxBuilder>>,
+        key_pool: Arc<AccessKeyPool>,
         tx_sender: Arc<TxSender>,
         account_id: AccountId,
+        config: Arc<Config>,
     ) -> Self {
         Self {
-            nonce_manager,
-            tx_builder,
+            key_pool,
             tx_sender,
             account_id,
+            config,
+            answer_strategy: AnswerStrategy::default(),
+        }
+    }
+
+    pub fn with_answer_strategy(mut self, answer_strategy: AnswerStrategy) -> Self {
+        self.answer_strategy = answer_strategy;
+        self
+    }
+
+    pub fn account_id(&self) -> &AccountId {
+        &self.account_id
+    }
+
+    fn answer_for(&self, request_id: &str) -> bool {
+        match self.answer_strategy {
+            AnswerStrategy::Yes => true,
+            AnswerStrategy::No => false,
+            AnswerStrategy::Random => {
+                let digest = hash(format!("{}:{}", self.account_id, request_id).as_bytes());
+                digest.as_ref()[0] & 1 == 1
+            }
         }
     }
 }
 
 #[async_trait]
 impl TransactionProcessor for Miner {
-    async fn process_transaction(
-        &self,
-        event_data: EventLog,
-    ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+    fn config(&self) -> &Config {
+        &self.config
+    }
+
+    async fn process_transaction(&self, event_data: EventLog) -> Result<bool, ClientError> {
         println!("Miner Processor");
         println!("Miner Event Data: {:?}", event_data);
 
-        let commit_attempts = 30;
-        let reveal_attempts = 30;
+        let commit_attempts = self.config.stage_attempts;
+        let reveal_attempts = self.config.stage_attempts;
         let mut committed = false;
 
         // Wait for CommitMiners stage
@@ -67,6 +104,10 @@ impl TransactionProcessor for Miner {
                         committed = true;
                         break;
                     }
+                    Err(e) if e.is_retryable() => {
+                        println!("Retrying commit by miner after: {}", e);
+                        sleep(self.config.poll_interval).await;
+                    }
                     Err(e) => {
                         println!("Failed to commit by miner: {}", e);
                         return Err(e);
@@ -81,7 +122,7 @@ impl TransactionProcessor for Miner {
                 return Ok(false);
             } else {
                 println!("Waiting for CommitMiners stage...");
-                sleep(Duration::from_secs(10)).await;
+                sleep(self.config.poll_interval).await;
             }
         }
 
@@ -103,6 +144,10 @@ impl TransactionProcessor for Miner {
                     Ok(_) => {
                         return Ok(true);
                     }
+                    Err(e) if e.is_retryable() => {
+                        println!("Retrying reveal by miner after: {}", e);
+                        sleep(self.config.poll_interval).await;
+                    }
                     Err(e) => {
                         println!("Failed to reveal by miner: {}", e);
                         return Err(e);
@@ -111,10 +156,13 @@ impl TransactionProcessor for Miner {
             } else if stage == "CommitValidators" || stage == "RevealValidators" || stage == "Ended"
             {
                 println!("RevealMiner stage has ended");
-                return Ok(false);
+                return Err(ClientError::WrongStage {
+                    expected: "RevealMiners".to_string(),
+                    actual: stage,
+                });
             } else {
                 println!("Waiting for RevealMiners stage...");
-                sleep(Duration::from_secs(10)).await;
+                sleep(self.config.poll_interval).await;
             }
         }
 
@@ -122,24 +170,20 @@ impl TransactionProcessor for Miner {
         Ok(false)
     }
 
-    async fn commit(
-        &self,
-        event_data: EventLog,
-    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    async fn commit(&self, event_data: EventLog) -> Result<(), ClientError> {
         println!("Miner Commit");
 
-        let request_id = event_data.data[0]["request_id"]
-            .as_str()
-            .unwrap_or_default()
-            .to_string();
+        let request_id = event_str(&event_data, "request_id")?;
+
+        let answer = self.answer_for(&request_id);
 
         // Query to obtain hash answer to commit
-        let query = QueryBuilder::new(ACCOUNT_TO_LISTEN.to_string())
+        let query = QueryBuilder::new(self.config.account_to_listen()?.to_string())
             .with_method_name("hash_miner_answer")
             .with_args(serde_json::json!({
                 "miner": self.account_id.to_string(),
                 "request_id": request_id,
-                "answer": true,
+                "answer": answer,
                 "message": "It's the best option",
             }))
             .build();
@@ -149,73 +193,61 @@ impl TransactionProcessor for Miner {
         let answer_hash = query_result.trim_matches('"');
 
         // Transaction to send the commit
-        let (nonce, block_hash) = self.nonce_manager.get_nonce_and_tx_hash().await?;
-
-        let mut tx_builder = self.tx_builder.lock().await;
+        let key = self.key_pool.acquire().await;
 
-        let request_id = event_data.data[0]["request_id"]
-            .as_str()
-            .unwrap_or_default()
-            .to_string();
-
-        let (tx, _) = tx_builder
-            .with_method_name("commit_by_miner")
-            .with_args(serde_json::json!({
+        let wait_until = self.config.method_profile("commit_by_miner").wait_until;
+        let tx = TxBuilder::new(self.config.protocol_contract.clone()).profiled_call(
+            &self.config,
+            "commit_by_miner",
+            serde_json::json!({
                 "request_id": request_id,
                 "answer": answer_hash,
-            }))
-            .build(nonce, block_hash);
-
-        let signer = &tx_builder.signer;
-
-        let request = methods::send_tx::RpcSendTransactionRequest {
-            signed_transaction: tx.sign(signer),
-            wait_until: TxExecutionStatus::Final,
-        };
-
-        let tx_response = self.tx_sender.send_transaction(request).await?;
-        let log_tx = self.extract_logs(&tx_response);
-
-        println!("COMMIT_MINER_LOG: {:?}", log_tx);
+            }),
+        );
+
+        let tx_response = self
+            .tx_sender
+            .submit(
+                tx,
+                wait_until.into(),
+                &key,
+                &TxContext::new("miner", &request_id),
+            )
+            .await?;
+        self.report_logs("COMMIT_MINER_LOG", &request_id, &tx_response);
 
         Ok(())
     }
 
-    async fn reveal(
-        &self,
-        event_data: EventLog,
-    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    async fn reveal(&self, event_data: EventLog) -> Result<(), ClientError> {
         println!("Reveal by miner");
 
         // Transaction to send the values to reveal
-        let (nonce, block_hash) = self.nonce_manager.get_nonce_and_tx_hash().await?;
-
-        let mut tx_builder = self.tx_builder.lock().await;
+        let key = self.key_pool.acquire().await;
 
-        let request_id = event_data.data[0]["request_id"]
-            .as_str()
-            .unwrap_or_default()
-            .to_string();
+        let request_id = event_str(&event_data, "request_id")?;
 
-        let (tx, _) = tx_builder
-            .with_method_name("reveal_by_miner")
-            .with_args(serde_json::json!({
+        let wait_until = self.config.method_profile("reveal_by_miner").wait_until;
+        let tx = TxBuilder::new(self.config.protocol_contract.clone()).profiled_call(
+            &self.config,
+            "reveal_by_miner",
+            serde_json::json!({
                 "request_id": request_id,
-                "answer": true,
+                "answer": self.answer_for(&request_id),
                 "message" : "It's the best option",
-            }))
-            .build(nonce, block_hash);
-
-        let signer = &tx_builder.signer;
-
-        let request = methods::send_tx::RpcSendTransactionRequest {
-            signed_transaction: tx.sign(signer),
-            wait_until: TxExecutionStatus::Final,
-        };
-
-        let tx_response = self.tx_sender.send_transaction(request).await?;
-        let log_tx = self.extract_logs(&tx_response);
-        println!("REVEAL_MINER_LOG: {:?}", log_tx);
+            }),
+        );
+
+        let tx_response = self
+            .tx_sender
+            .submit(
+                tx,
+                wait_until.into(),
+                &key,
+                &TxContext::new("miner", &request_id),
+            )
+            .await?;
+        self.report_logs("REVEAL_MINER_LOG", &request_id, &tx_response);
 
         Ok(())
     }
@@ -1,10 +1,12 @@
 mod aggregator;
+mod fleet;
 mod miner;
 mod transaction_processor;
 mod utils;
 mod validator;
 
-pub use aggregator::Aggregator;
-pub use miner::Miner;
-pub use transaction_processor::TransactionProcessor;
+pub use aggregator::{obtain_top_ten, Aggregator};
+pub use fleet::MinerFleet;
+pub use miner::{AnswerStrategy, Miner};
+pub use transaction_processor::{extract_logs, parse_event, query_stage, TransactionProcessor};
 pub use validator::Validator;
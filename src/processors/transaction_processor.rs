@@ -1,76 +1,145 @@
-use crate::constants::ACCOUNT_TO_LISTEN;
+use crate::config::Config;
+use crate::error::ClientError;
 use crate::qx_builder::QueryBuilder;
 use crate::qx_sender::QuerySender;
+use crate::rpc_pool::RpcPool;
+use crate::tx_sender::final_outcome;
 
 use async_trait::async_trait;
 use near_event_listener::EventLog;
-use near_jsonrpc_client::JsonRpcClient;
 use near_jsonrpc_primitives::types::transactions::RpcTransactionResponse;
-use near_primitives::views::FinalExecutionOutcomeViewEnum;
+use near_primitives::hash::CryptoHash;
+use near_primitives::types::AccountId;
 use std::sync::Arc;
 
+/// A log line with the account and receipt that emitted it. For the transaction's own outcome
+/// `id` is the transaction hash.
+pub struct OutcomeLog {
+    pub executor_id: AccountId,
+    pub id: CryptoHash,
+    pub log: String,
+    /// Set for NEP-297 `EVENT_JSON:` lines
+    pub event: Option<EventLog>,
+}
+
 #[async_trait]
 pub trait TransactionProcessor: Send + Sync {
-    async fn process_transaction(
-        &self,
-        event_data: EventLog,
-    ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>>;
+    fn config(&self) -> &Config;
 
-    async fn commit(
-        &self,
-        event_data: EventLog,
-    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+    async fn process_transaction(&self, event_data: EventLog) -> Result<bool, ClientError>;
 
-    async fn reveal(
-        &self,
-        event_data: EventLog,
-    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+    async fn commit(&self, event_data: EventLog) -> Result<(), ClientError>;
+
+    async fn reveal(&self, event_data: EventLog) -> Result<(), ClientError>;
 
     // Default methods to all the implementations to synchronize
     async fn get_stage(
         &self,
-        tx_sender: Arc<JsonRpcClient>,
+        tx_sender: Arc<RpcPool>,
         event_data: EventLog,
-    ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+    ) -> Result<String, ClientError> {
         println!("Get Stage");
 
-        let start_time = event_data.data[0]["start_time"]
-            .as_u64()
-            .unwrap_or_default();
-
-        let query = QueryBuilder::new(ACCOUNT_TO_LISTEN.to_string())
-            .with_method_name("get_stage")
-            .with_args(serde_json::json!({
-                "start_time": start_time,
-            }))
-            .build();
-
-        let query_sender = QuerySender::new(tx_sender);
-        let stage = query_sender.send_query(query).await?;
+        let start_time = event_u64(&event_data, "start_time")?;
 
-        Ok(stage)
+        query_stage(tx_sender, self.config(), start_time).await
     }
 
-    fn extract_logs(&self, response: &RpcTransactionResponse) -> Vec<String> {
-        let mut logs = Vec::new();
-
-        if let Some(final_outcome_enum) = &response.final_execution_outcome {
-            match final_outcome_enum {
-                FinalExecutionOutcomeViewEnum::FinalExecutionOutcome(final_outcome) => {
-                    logs.extend(final_outcome.transaction_outcome.outcome.logs.clone());
-
-                    for receipt_outcome in &final_outcome.receipts_outcome {
-                        logs.extend(receipt_outcome.outcome.logs.clone());
-                    }
-                }
-                FinalExecutionOutcomeViewEnum::FinalExecutionOutcomeWithReceipt(
-                    final_outcome_with_receipt,
-                ) => {
-                    println!("Non-handled case: {:?}", final_outcome_with_receipt);
-                }
+    /// Prints the logs of a transaction sent for `request_id`, with NEP-297 events shown by
+    /// name. An event naming another request is reported as an error, as the contract then
+    /// handled the call for a request other than the one it was meant for.
+    fn report_logs(&self, label: &str, request_id: &str, response: &RpcTransactionResponse) {
+        for log in extract_logs(response) {
+            let Some(event) = &log.event else {
+                println!("{}: {} ({}): {}", label, log.executor_id, log.id, log.log);
+                continue;
+            };
+
+            println!(
+                "{}: {} ({}): event {} {} {}: {}",
+                label,
+                log.executor_id,
+                log.id,
+                event.standard,
+                event.version,
+                event.event,
+                event.data
+            );
+            let event_request_id = event
+                .data
+                .get(0)
+                .and_then(|entry| entry["request_id"].as_str());
+            if let Some(event_request_id) = event_request_id.filter(|id| *id != request_id) {
+                eprintln!(
+                    "{}: event {} is for request {}, expected {}",
+                    label, event.event, event_request_id, request_id
+                );
             }
         }
-
-        logs
     }
 }
+
+/// Logs of the transaction and all of its receipts, in execution order.
+pub fn extract_logs(response: &RpcTransactionResponse) -> Vec<OutcomeLog> {
+    let Some(final_outcome) = final_outcome(response) else {
+        return Vec::new();
+    };
+
+    std::iter::once(&final_outcome.transaction_outcome)
+        .chain(&final_outcome.receipts_outcome)
+        .flat_map(|outcome| {
+            outcome.outcome.logs.iter().map(|log| OutcomeLog {
+                executor_id: outcome.outcome.executor_id.clone(),
+                id: outcome.id,
+                event: parse_event(log),
+                log: log.clone(),
+            })
+        })
+        .collect()
+}
+
+pub async fn query_stage(
+    client: Arc<RpcPool>,
+    config: &Config,
+    start_time: u64,
+) -> Result<String, ClientError> {
+    let query = QueryBuilder::new(config.account_to_listen()?.to_string())
+        .with_method_name("get_stage")
+        .with_args(serde_json::json!({
+            "start_time": start_time,
+        }))
+        .build();
+
+    let query_sender = QuerySender::new(client);
+    let stage = query_sender.send_query(query).await?;
+
+    Ok(stage)
+}
+
+/// Reads a string field from the first entry of an event's data.
+pub fn event_str(event_data: &EventLog, field: &str) -> Result<String, ClientError> {
+    event_data.data[0][field]
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| ClientError::MalformedEvent(format!("missing string field `{}`", field)))
+}
+
+/// Reads an integer field from the first entry of an event's data.
+pub fn event_u64(event_data: &EventLog, field: &str) -> Result<u64, ClientError> {
+    event_data.data[0][field]
+        .as_u64()
+        .ok_or_else(|| ClientError::MalformedEvent(format!("missing integer field `{}`", field)))
+}
+
+/// Parses a NEP-297 `EVENT_JSON:{...}` log line.
+pub fn parse_event(log: &str) -> Option<EventLog> {
+    let json: serde_json::Value =
+        serde_json::from_str(log.strip_prefix("EVENT_JSON:")?.trim()).ok()?;
+
+    Some(EventLog {
+        standard: json["standard"].as_str()?.to_string(),
+        version: json["version"].as_str()?.to_string(),
+        event: json["event"].as_str()?.to_string(),
+        data: json["data"].clone(),
+    })
+}
@@ -1,59 +1,60 @@
 //use crate::block_streamer::extract_logs;
-use crate::constants::ACCOUNT_TO_LISTEN;
 use near_event_listener::EventLog;
 
-use crate::nonce_manager::NonceManager;
+use crate::config::Config;
+use crate::error::ClientError;
+use crate::journal::TxContext;
+use crate::key_pool::AccessKeyPool;
 use crate::qx_builder::QueryBuilder;
 use crate::qx_sender::QuerySender;
 use crate::tx_builder::TxBuilder;
 use crate::tx_sender::TxSender;
 
 use async_trait::async_trait;
-use near_jsonrpc_client::methods;
-use near_primitives::views::TxExecutionStatus;
 use near_sdk::AccountId;
 
 use std::sync::Arc;
-use tokio::sync::Mutex;
-use tokio::time::{sleep, Duration};
+use tokio::time::sleep;
 
+use super::transaction_processor::event_str;
 use super::utils;
 use super::TransactionProcessor;
 
 pub struct Validator {
-    nonce_manager: Arc<NonceManager>,
-    tx_builder: Arc<Mutex<TxBuilder>>,
+    key_pool: Arc<AccessKeyPool>,
     tx_sender: Arc<TxSender>,
     account_id: AccountId,
+    config: Arc<Config>,
 }
 
 impl Validator {
     pub fn new(
-        nonce_manager: Arc<NonceManager>,
-        tx_builder: Arc<Mutex<TxBuilder>>,
+        key_pool: Arc<AccessKeyPool>,
         tx_sender: Arc<TxSender>,
         account_id: AccountId,
+        config: Arc<Config>,
     ) -> Self {
         Self {
-            nonce_manager,
-            tx_builder,
+            key_pool,
             tx_sender,
             account_id,
+            config,
         }
     }
 }
 
 #[async_trait]
 impl TransactionProcessor for Validator {
-    async fn process_transaction(
-        &self,
-        event_data: EventLog,
-    ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+    fn config(&self) -> &Config {
+        &self.config
+    }
+
+    async fn process_transaction(&self, event_data: EventLog) -> Result<bool, ClientError> {
         println!("Validator Processor");
         println!("Validator Event Data: {:?}", event_data);
 
-        let commit_attempts = 30;
-        let reveal_attempts = 30;
+        let commit_attempts = self.config.stage_attempts;
+        let reveal_attempts = self.config.stage_attempts;
         let mut committed = false;
 
         for _attempt in 0..commit_attempts {
@@ -70,6 +71,10 @@ impl TransactionProcessor for Validator {
                         committed = true;
                         break;
                     }
+                    Err(e) if e.is_retryable() => {
+                        println!("Retrying commit by validator after: {}", e);
+                        sleep(self.config.poll_interval).await;
+                    }
                     Err(e) => {
                         println!("Failed to commit by validator: {}", e);
                         return Err(e);
@@ -80,7 +85,7 @@ impl TransactionProcessor for Validator {
                 return Ok(false);
             } else {
                 println!("Waiting for CommitValidators stage...");
-                sleep(Duration::from_secs(10)).await;
+                sleep(self.config.poll_interval).await;
             }
         }
 
@@ -101,6 +106,10 @@ impl TransactionProcessor for Validator {
                     Ok(_) => {
                         return Ok(true);
                     }
+                    Err(e) if e.is_retryable() => {
+                        println!("Retrying reveal by validator after: {}", e);
+                        sleep(self.config.poll_interval).await;
+                    }
                     Err(e) => {
                         println!("Failed to reveal by validator: {}", e);
                         return Err(e);
@@ -108,10 +117,13 @@ impl TransactionProcessor for Validator {
                 }
             } else if stage == "Ended" {
                 println!("RevealValidators stage has ended...");
-                return Ok(false);
+                return Err(ClientError::WrongStage {
+                    expected: "RevealValidators".to_string(),
+                    actual: stage,
+                });
             } else {
                 println!("Waiting for RevealValidators stage...");
-                sleep(Duration::from_secs(10)).await;
+                sleep(self.config.poll_interval).await;
             }
         }
 
@@ -119,18 +131,12 @@ impl TransactionProcessor for Validator {
         Ok(false)
     }
 
-    async fn commit(
-        &self,
-        event_data: EventLog,
-    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    async fn commit(&self, event_data: EventLog) -> Result<(), ClientError> {
         println!("Validator Commit");
 
-        let request_id = event_data.data[0]["request_id"]
-            .as_str()
-            .unwrap_or_default()
-            .to_string();
+        let request_id = event_str(&event_data, "request_id")?;
 
-        let query = QueryBuilder::new(ACCOUNT_TO_LISTEN.to_string())
+        let query = QueryBuilder::new(self.config.account_to_listen()?.to_string())
             .with_method_name("get_list_miners_that_commit_and_reveal")
             .with_args(serde_json::json!({
                 "request_id": request_id
@@ -144,7 +150,7 @@ impl TransactionProcessor for Validator {
 
         let answer = utils::generate_validator_answer();
 
-        let query = QueryBuilder::new(ACCOUNT_TO_LISTEN.to_string())
+        let query = QueryBuilder::new(self.config.account_to_listen()?.to_string())
             .with_method_name("hash_validator_answer")
             .with_args(serde_json::json!({
                 "validator": self.account_id.to_string(),
@@ -157,67 +163,60 @@ impl TransactionProcessor for Validator {
         let query_sender = QuerySender::new(self.tx_sender.client.clone());
         let query_result = query_sender.send_query(query).await?;
 
-        let (nonce, block_hash) = self.nonce_manager.get_nonce_and_tx_hash().await?;
+        let key = self.key_pool.acquire().await;
 
-        let mut tx_builder = self.tx_builder.lock().await;
-
-        let (tx, _) = tx_builder
-            .with_method_name("commit_by_validator")
-            .with_args(serde_json::json!({
+        let wait_until = self.config.method_profile("commit_by_validator").wait_until;
+        let tx = TxBuilder::new(self.config.protocol_contract.clone()).profiled_call(
+            &self.config,
+            "commit_by_validator",
+            serde_json::json!({
                 "request_id": request_id,
                 "answer": query_result,
-            }))
-            .build(nonce, block_hash);
-
-        let signer = &tx_builder.signer;
-
-        let request = methods::send_tx::RpcSendTransactionRequest {
-            signed_transaction: tx.sign(signer),
-            wait_until: TxExecutionStatus::Final,
-        };
-
-        let tx_response = self.tx_sender.send_transaction(request).await?;
-        let log_tx = self.extract_logs(&tx_response);
-
-        println!("COMMIT_VALIDATOR_LOG: {:?}", log_tx);
+            }),
+        );
+
+        let tx_response = self
+            .tx_sender
+            .submit(
+                tx,
+                wait_until.into(),
+                &key,
+                &TxContext::new("validator", &request_id),
+            )
+            .await?;
+        self.report_logs("COMMIT_VALIDATOR_LOG", &request_id, &tx_response);
 
         Ok(())
     }
 
-    async fn reveal(
-        &self,
-        event_data: EventLog,
-    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    async fn reveal(&self, event_data: EventLog) -> Result<(), ClientError> {
         println!("Reveal by validator");
 
-        let (nonce, block_hash) = self.nonce_manager.get_nonce_and_tx_hash().await?;
+        let key = self.key_pool.acquire().await;
 
-        let mut tx_builder = self.tx_builder.lock().await;
+        let request_id = event_str(&event_data, "request_id")?;
 
-        let request_id = event_data.data[0]["request_id"]
-            .as_str()
-            .unwrap_or_default()
-            .to_string();
-
-        let (tx, _) = tx_builder
-            .with_method_name("reveal_by_validator")
-            .with_args(serde_json::json!({
+        let wait_until = self.config.method_profile("reveal_by_validator").wait_until;
+        let tx = TxBuilder::new(self.config.protocol_contract.clone()).profiled_call(
+            &self.config,
+            "reveal_by_validator",
+            serde_json::json!({
                 "request_id": request_id,
                 "answer": utils::generate_validator_answer(),
                 "message": "This are the best miners",
-            }))
-            .build(nonce, block_hash);
-
-        let signer = &tx_builder.signer;
-
-        let request = methods::send_tx::RpcSendTransactionRequest {
-            signed_transaction: tx.sign(signer),
-            wait_until: TxExecutionStatus::Final,
-        };
-
-        let tx_response = self.tx_sender.send_transaction(request).await?;
-        let log_tx = self.extract_logs(&tx_response);
-        println!("REVEAL_VALIDATOR_LOG: {:?}", log_tx);
+            }),
+        );
+
+        let tx_response = self
+            .tx_sender
+            .submit(
+                tx,
+                wait_until.into(),
+                &key,
+                &TxContext::new("validator", &request_id),
+            )
+            .await?;
+        self.report_logs("REVEAL_VALIDATOR_LOG", &request_id, &tx_response);
 
         Ok(())
     }
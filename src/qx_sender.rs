@@ -1,28 +1,33 @@
-use near_jsonrpc_client::{methods, JsonRpcClient};
+use near_jsonrpc_client::methods;
 use near_jsonrpc_primitives::types::query::QueryResponseKind;
-use std::error::Error;
 use std::sync::Arc;
 
+use crate::error::ClientError;
+use crate::rpc_pool::RpcPool;
+
 pub struct QuerySender {
-    client: Arc<JsonRpcClient>,
+    client: Arc<RpcPool>,
 }
 
 impl QuerySender {
-    pub fn new(client: Arc<JsonRpcClient>) -> Self {
+    pub fn new(client: Arc<RpcPool>) -> Self {
         Self { client }
     }
 
     pub async fn send_query(
         &self,
         request: methods::query::RpcQueryRequest,
-    ) -> Result<String, Box<dyn Error + Send + Sync>> {
+    ) -> Result<String, ClientError> {
         let response = self.client.call(request).await?;
 
         if let QueryResponseKind::CallResult(result) = response.kind {
-            let result_str = String::from_utf8(result.result)?;
+            let result_str = String::from_utf8(result.result)
+                .map_err(|err| ClientError::UnexpectedResponse(err.to_string()))?;
             Ok(result_str)
         } else {
-            Err("Unexpected response kind".into())
+            Err(ClientError::UnexpectedResponse(
+                "expected a function call result".to_string(),
+            ))
         }
     }
 }
@@ -0,0 +1,160 @@
+use near_jsonrpc_primitives::types::transactions::RpcTransactionResponse;
+use near_primitives::action::delegate::SignedDelegateAction;
+use near_primitives::views::{
+    FinalExecutionOutcomeView, FinalExecutionOutcomeViewEnum, TxExecutionStatus,
+};
+
+use crate::constants::RELAYER_TIMEOUT;
+use crate::error::ClientError;
+
+/// HTTP client for a NEP-366 relayer, which wraps signed delegate actions in transactions it
+/// signs and pays for.
+///
+/// Delegate actions are posted as JSON to `<url>/send_meta_tx`. A relayer that answers with the
+/// final execution outcome lets the caller read logs and failures as for a direct transaction.
+pub struct Relayer {
+    url: String,
+    http: reqwest::Client,
+}
+
+impl Relayer {
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: format!("{}/send_meta_tx", url.trim_end_matches('/')),
+            http: reqwest::Client::builder()
+                .timeout(RELAYER_TIMEOUT)
+                .build()
+                .expect("the relayer HTTP client always builds"),
+        }
+    }
+
+    pub async fn send(
+        &self,
+        signed_delegate_action: &SignedDelegateAction,
+    ) -> Result<RpcTransactionResponse, ClientError> {
+        let response = self
+            .http
+            .post(&self.url)
+            .json(signed_delegate_action)
+            .send()
+            .await
+            .map_err(|err| ClientError::RpcTransport(format!("relayer {}: {}", self.url, err)))?;
+
+        let status = response.status();
+        let body = response
+            .text()
+            .await
+            .map_err(|err| ClientError::RpcTransport(format!("relayer {}: {}", self.url, err)))?;
+
+        if status.is_server_error() {
+            return Err(ClientError::RpcTransport(format!(
+                "relayer {} returned {}: {}",
+                self.url, status, body
+            )));
+        }
+        if !status.is_success() {
+            return Err(ClientError::Relayer(format!("{}: {}", status, body)));
+        }
+
+        match serde_json::from_str::<FinalExecutionOutcomeView>(&body) {
+            Ok(outcome) => Ok(RpcTransactionResponse {
+                final_execution_outcome: Some(
+                    FinalExecutionOutcomeViewEnum::FinalExecutionOutcome(outcome),
+                ),
+                final_execution_status: TxExecutionStatus::Final,
+            }),
+            Err(_) => {
+                println!("Relayer accepted the delegate action: {}", body);
+                Ok(RpcTransactionResponse {
+                    final_execution_outcome: None,
+                    final_execution_status: TxExecutionStatus::None,
+                })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_crypto::{KeyType, PublicKey, Signature};
+    use near_primitives::action::delegate::DelegateAction;
+    use serde_json::{json, Value};
+
+    use crate::test_server::{outcome_result, transaction_view, StandIn};
+    use crate::tx_sender::final_outcome;
+
+    fn signed_delegate_action() -> SignedDelegateAction {
+        SignedDelegateAction {
+            delegate_action: DelegateAction {
+                sender_id: "miner.test.near".parse().unwrap(),
+                receiver_id: "earthmindprotocol.test.near".parse().unwrap(),
+                actions: Vec::new(),
+                nonce: 1,
+                max_block_height: 300,
+                public_key: PublicKey::empty(KeyType::ED25519),
+            },
+            signature: Signature::empty(KeyType::ED25519),
+        }
+    }
+
+    async fn relayer(status: u16, body: Value) -> StandIn {
+        StandIn::start(move |path, request| {
+            if path != "/send_meta_tx" {
+                return (404, Value::Null);
+            }
+            assert_eq!(
+                request["delegate_action"]["sender_id"], "miner.test.near",
+                "the signed delegate action is posted as JSON"
+            );
+            (status, body.clone())
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn returns_the_final_outcome() {
+        let transaction = transaction_view(
+            "relayed",
+            "relayer.test.near",
+            "miner.test.near",
+            "commit_by_miner",
+        );
+        let server = relayer(200, outcome_result(transaction, &["relayed"])).await;
+
+        let response = Relayer::new(&format!("{}/", server.url))
+            .send(&signed_delegate_action())
+            .await
+            .unwrap();
+
+        assert_eq!(server.hits(), 1);
+        assert_eq!(response.final_execution_status, TxExecutionStatus::Final);
+        let outcome = final_outcome(&response).expect("the outcome is kept");
+        assert_eq!(outcome.transaction_outcome.outcome.logs, ["relayed"]);
+    }
+
+    #[tokio::test]
+    async fn rejection_is_a_relayer_error() {
+        let server = relayer(400, json!("delegate action expired")).await;
+
+        let result = Relayer::new(&server.url)
+            .send(&signed_delegate_action())
+            .await;
+
+        match result {
+            Err(ClientError::Relayer(message)) => assert!(message.contains("expired")),
+            other => panic!("expected a relayer error, got {:?}", other.err()),
+        }
+    }
+
+    #[tokio::test]
+    async fn server_error_is_a_transport_error() {
+        let server = relayer(502, json!("bad gateway")).await;
+
+        let result = Relayer::new(&server.url)
+            .send(&signed_delegate_action())
+            .await;
+
+        assert!(matches!(result, Err(ClientError::RpcTransport(_))));
+    }
+}
@@ -0,0 +1,317 @@
+use near_jsonrpc_client::errors::{JsonRpcError, JsonRpcServerError};
+use near_jsonrpc_client::methods::{self, RpcMethod};
+use near_jsonrpc_client::{JsonRpcClient, MethodCallResult};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::constants::{RPC_COOLDOWN_BASE, RPC_COOLDOWN_MAX};
+
+/// A set of RPC endpoints for the same network. Calls go to the healthiest, fastest endpoint
+/// and fail over to the next one when an endpoint cannot be reached.
+pub struct RpcPool {
+    endpoints: Vec<Endpoint>,
+}
+
+struct Endpoint {
+    url: String,
+    client: JsonRpcClient,
+    health: Mutex<Health>,
+}
+
+#[derive(Default)]
+struct Health {
+    consecutive_failures: u32,
+    latency: Option<Duration>,
+    down_until: Option<Instant>,
+}
+
+impl RpcPool {
+    /// A call to an endpoint that takes longer than `timeout` counts as a failure of that
+    /// endpoint.
+    pub fn new(urls: &[String], timeout: Duration) -> Self {
+        assert!(!urls.is_empty(), "RpcPool needs at least one endpoint");
+
+        let http = reqwest::Client::builder()
+            .timeout(timeout)
+            .build()
+            .expect("the RPC HTTP client always builds");
+        let connector = JsonRpcClient::with(http);
+
+        let endpoints = urls
+            .iter()
+            .map(|url| Endpoint {
+                url: url.clone(),
+                client: connector.connect(url.as_str()),
+                health: Mutex::new(Health::default()),
+            })
+            .collect();
+
+        Self { endpoints }
+    }
+
+    /// Sends `method` to the endpoints in order of preference until one of them answers.
+    ///
+    /// Only endpoint failures (transport errors, internal errors, bad HTTP status) trigger a
+    /// failover; handler errors such as an invalid transaction are returned as they are.
+    pub async fn call<M>(&self, method: M) -> MethodCallResult<M::Response, M::Error>
+    where
+        M: RpcMethod + Clone,
+    {
+        let mut last_err = None;
+
+        for endpoint in self.ordered() {
+            let started = Instant::now();
+
+            match endpoint.client.call(method.clone()).await {
+                Err(err) if is_endpoint_failure(&err) => {
+                    let cooldown = endpoint.mark_down();
+                    eprintln!(
+                        "RPC endpoint {} failed, skipping it for {}s",
+                        endpoint.url,
+                        cooldown.as_secs()
+                    );
+                    last_err = Some(err);
+                }
+                result => {
+                    endpoint.mark_up(started.elapsed());
+                    return result;
+                }
+            }
+        }
+
+        Err(last_err.expect("RpcPool has at least one endpoint"))
+    }
+
+    /// Probes every endpoint with a status request, bringing recovered endpoints back.
+    pub async fn run_health_checks(self: Arc<Self>, interval: Duration) {
+        loop {
+            tokio::time::sleep(interval).await;
+
+            for endpoint in &self.endpoints {
+                let started = Instant::now();
+                match endpoint
+                    .client
+                    .call(methods::status::RpcStatusRequest)
+                    .await
+                {
+                    Ok(_) => endpoint.mark_up(started.elapsed()),
+                    Err(_) => {
+                        endpoint.mark_down();
+                    }
+                }
+            }
+
+            for endpoint in &self.endpoints {
+                let health = endpoint.health.lock().unwrap();
+                println!(
+                    "RPC {}: {}, latency {:?}",
+                    endpoint.url,
+                    if health.is_available(Instant::now()) {
+                        "up"
+                    } else {
+                        "down"
+                    },
+                    health.latency
+                );
+            }
+        }
+    }
+
+    /// Available endpoints by latency, then the ones still cooling down. Endpoints without a
+    /// latency sample keep their configured order.
+    fn ordered(&self) -> Vec<&Endpoint> {
+        let now = Instant::now();
+        let mut endpoints: Vec<&Endpoint> = self.endpoints.iter().collect();
+
+        endpoints.sort_by_key(|endpoint| {
+            let health = endpoint.health.lock().unwrap();
+            (
+                !health.is_available(now),
+                health.latency.unwrap_or(Duration::MAX),
+            )
+        });
+
+        endpoints
+    }
+}
+
+impl Endpoint {
+    fn mark_up(&self, latency: Duration) {
+        let mut health = self.health.lock().unwrap();
+        health.consecutive_failures = 0;
+        health.down_until = None;
+        health.latency = Some(match health.latency {
+            Some(previous) => (previous * 7 + latency * 3) / 10,
+            None => latency,
+        });
+    }
+
+    fn mark_down(&self) -> Duration {
+        let mut health = self.health.lock().unwrap();
+        health.consecutive_failures += 1;
+
+        let cooldown = RPC_COOLDOWN_BASE
+            .saturating_mul(1u32 << health.consecutive_failures.min(16).saturating_sub(1))
+            .min(RPC_COOLDOWN_MAX);
+        health.down_until = Some(Instant::now() + cooldown);
+
+        cooldown
+    }
+}
+
+impl Health {
+    fn is_available(&self, now: Instant) -> bool {
+        !matches!(self.down_until, Some(until) if until > now)
+    }
+}
+
+fn is_endpoint_failure<E>(err: &JsonRpcError<E>) -> bool {
+    matches!(
+        err,
+        JsonRpcError::TransportError(_)
+            | JsonRpcError::ServerError(JsonRpcServerError::InternalError { .. })
+            | JsonRpcError::ServerError(JsonRpcServerError::ResponseStatusError(_))
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use crate::test_server::{rpc_result, status_result, unreachable_url, StandIn};
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    async fn healthy() -> StandIn {
+        StandIn::start(|_, request| (200, rpc_result(request, status_result()))).await
+    }
+
+    fn urls(pool: &RpcPool) -> Vec<&str> {
+        pool.ordered()
+            .into_iter()
+            .map(|endpoint| endpoint.url.as_str())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn fails_over_when_an_endpoint_is_unreachable() {
+        let down = unreachable_url().await;
+        let up = healthy().await;
+        let pool = RpcPool::new(&[down.clone(), up.url.clone()], TIMEOUT);
+
+        pool.call(methods::status::RpcStatusRequest).await.unwrap();
+
+        assert_eq!(up.hits(), 1);
+        assert_eq!(urls(&pool), [up.url.as_str(), down.as_str()]);
+    }
+
+    #[tokio::test]
+    async fn skips_an_endpoint_cooling_down_after_a_5xx() {
+        let failing = StandIn::start(|_, _| (503, serde_json::Value::Null)).await;
+        let up = healthy().await;
+        let pool = RpcPool::new(&[failing.url.clone(), up.url.clone()], TIMEOUT);
+
+        pool.call(methods::status::RpcStatusRequest).await.unwrap();
+        pool.call(methods::status::RpcStatusRequest).await.unwrap();
+
+        assert_eq!(failing.hits(), 1);
+        assert_eq!(up.hits(), 2);
+        assert_eq!(
+            pool.endpoints[0]
+                .health
+                .lock()
+                .unwrap()
+                .consecutive_failures,
+            1
+        );
+        assert_eq!(urls(&pool), [up.url.as_str(), failing.url.as_str()]);
+    }
+
+    #[tokio::test]
+    async fn orders_available_endpoints_by_latency_then_cooling_ones() {
+        let pool = RpcPool::new(
+            &[
+                "http://slow".to_string(),
+                "http://fast".to_string(),
+                "http://down".to_string(),
+                "http://unmeasured".to_string(),
+            ],
+            TIMEOUT,
+        );
+        pool.endpoints[0].mark_up(Duration::from_millis(300));
+        pool.endpoints[1].mark_up(Duration::from_millis(20));
+        pool.endpoints[2].mark_up(Duration::from_millis(1));
+        pool.endpoints[2].mark_down();
+
+        assert_eq!(
+            urls(&pool),
+            [
+                "http://fast",
+                "http://slow",
+                "http://unmeasured",
+                "http://down"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn cooldown_doubles_up_to_the_maximum() {
+        let pool = RpcPool::new(&["http://down".to_string()], TIMEOUT);
+        let endpoint = &pool.endpoints[0];
+
+        assert_eq!(endpoint.mark_down(), RPC_COOLDOWN_BASE);
+        assert_eq!(endpoint.mark_down(), RPC_COOLDOWN_BASE * 2);
+        for _ in 0..20 {
+            endpoint.mark_down();
+        }
+        assert_eq!(endpoint.mark_down(), RPC_COOLDOWN_MAX);
+
+        // An endpoint still cooling down is tried as a last resort
+        assert_eq!(urls(&pool), ["http://down"]);
+    }
+
+    #[tokio::test]
+    async fn health_checks_bring_a_recovered_endpoint_back() {
+        let recovered = Arc::new(AtomicBool::new(false));
+        let flaky = {
+            let recovered = recovered.clone();
+            StandIn::start(move |_, request| {
+                if recovered.load(Ordering::SeqCst) {
+                    (200, rpc_result(request, status_result()))
+                } else {
+                    (500, serde_json::Value::Null)
+                }
+            })
+            .await
+        };
+        let up = healthy().await;
+        let pool = Arc::new(RpcPool::new(&[flaky.url.clone(), up.url.clone()], TIMEOUT));
+
+        pool.call(methods::status::RpcStatusRequest).await.unwrap();
+        assert!(!pool.endpoints[0]
+            .health
+            .lock()
+            .unwrap()
+            .is_available(Instant::now()));
+
+        recovered.store(true, Ordering::SeqCst);
+        let checks = tokio::spawn(pool.clone().run_health_checks(Duration::from_millis(50)));
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !pool.endpoints[0]
+            .health
+            .lock()
+            .unwrap()
+            .is_available(Instant::now())
+        {
+            assert!(Instant::now() < deadline, "endpoint never came back");
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        checks.abort();
+
+        let health = pool.endpoints[0].health.lock().unwrap();
+        assert_eq!(health.consecutive_failures, 0);
+        assert!(health.latency.is_some());
+    }
+}
@@ -0,0 +1,378 @@
+use async_trait::async_trait;
+use near_crypto::{InMemorySigner, PublicKey, Signature, Signer};
+use near_primitives::serialize::to_base64;
+use near_primitives::transaction::{SignedTransaction, Transaction};
+use near_sdk::AccountId;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::str::FromStr;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::UnixStream;
+
+use crate::constants::SIGNER_TIMEOUT;
+use crate::error::ClientError;
+
+/// Signs transactions and delegate actions for one access key.
+///
+/// Key files and the encrypted keystore are unlocked into an `InMemorySigner`; a
+/// `RemoteSigner` leaves the key in a separate process. Signing is async so that the remote
+/// round trip does not block the runtime.
+#[async_trait]
+pub trait TxSigner: Send + Sync {
+    fn account_id(&self) -> &AccountId;
+
+    fn public_key(&self) -> &PublicKey;
+
+    /// Signs `data`, which is always a 32-byte transaction or NEP-461 hash.
+    async fn sign(&self, data: &[u8]) -> Result<Signature, ClientError>;
+}
+
+#[async_trait]
+impl TxSigner for InMemorySigner {
+    fn account_id(&self) -> &AccountId {
+        &self.account_id
+    }
+
+    fn public_key(&self) -> &PublicKey {
+        &self.public_key
+    }
+
+    async fn sign(&self, data: &[u8]) -> Result<Signature, ClientError> {
+        Ok(Signer::sign(self, data))
+    }
+}
+
+/// Signs the hash of `transaction`, as `Transaction::sign` does for a local key.
+pub async fn sign_transaction(
+    signer: &dyn TxSigner,
+    transaction: Transaction,
+) -> Result<SignedTransaction, ClientError> {
+    let (hash, _) = transaction.get_hash_and_size();
+    let signature = signer.sign(hash.as_ref()).await?;
+    Ok(SignedTransaction::new(signature, transaction))
+}
+
+/// Identifies a key without being able to sign, for transactions exported unsigned.
+pub struct WatchOnlySigner {
+    account_id: AccountId,
+    public_key: PublicKey,
+}
+
+impl WatchOnlySigner {
+    pub fn new(account_id: AccountId, public_key: PublicKey) -> Self {
+        Self {
+            account_id,
+            public_key,
+        }
+    }
+}
+
+#[async_trait]
+impl TxSigner for WatchOnlySigner {
+    fn account_id(&self) -> &AccountId {
+        &self.account_id
+    }
+
+    fn public_key(&self) -> &PublicKey {
+        &self.public_key
+    }
+
+    async fn sign(&self, _data: &[u8]) -> Result<Signature, ClientError> {
+        Err(ClientError::Signer(format!(
+            "{} is watch-only and cannot sign",
+            self.public_key
+        )))
+    }
+}
+
+/// Where a `RemoteSigner` listens: `unix:<path>` or an http(s) URL.
+#[derive(Clone, Debug)]
+pub enum RemoteEndpoint {
+    Unix(PathBuf),
+    Http(String),
+}
+
+impl FromStr for RemoteEndpoint {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        if let Some(path) = value.strip_prefix("unix:") {
+            Ok(RemoteEndpoint::Unix(PathBuf::from(path)))
+        } else if value.starts_with("http://") || value.starts_with("https://") {
+            Ok(RemoteEndpoint::Http(value.to_string()))
+        } else {
+            Err(format!(
+                "`{}` is neither `unix:<path>` nor an http(s) URL",
+                value
+            ))
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(tag = "method", rename_all = "snake_case")]
+enum SignerRequest<'a> {
+    PublicKey {
+        account_id: &'a AccountId,
+    },
+    Sign {
+        account_id: &'a AccountId,
+        public_key: &'a PublicKey,
+        /// Base64
+        data: String,
+    },
+}
+
+#[derive(Deserialize)]
+struct SignerResponse {
+    public_key: Option<PublicKey>,
+    signature: Option<Signature>,
+    error: Option<String>,
+}
+
+/// Key held by a separate signing process.
+///
+/// Each request is one JSON object: sent as a single line over a fresh Unix socket connection,
+/// or POSTed to the HTTP endpoint. The answer is `{"public_key": ...}`, `{"signature": ...}` or
+/// `{"error": ...}`, and must arrive within `SIGNER_TIMEOUT`.
+pub struct RemoteSigner {
+    endpoint: RemoteEndpoint,
+    account_id: AccountId,
+    public_key: PublicKey,
+    http: reqwest::Client,
+}
+
+impl RemoteSigner {
+    /// Asks the signer which key it holds for `account_id`.
+    pub async fn connect(
+        endpoint: RemoteEndpoint,
+        account_id: AccountId,
+    ) -> Result<Self, ClientError> {
+        let http = reqwest::Client::builder()
+            .timeout(SIGNER_TIMEOUT)
+            .build()
+            .expect("the signer HTTP client always builds");
+        let response = call(
+            &endpoint,
+            &http,
+            &SignerRequest::PublicKey {
+                account_id: &account_id,
+            },
+        )
+        .await?;
+        let public_key = response.public_key.ok_or_else(|| {
+            ClientError::Signer(format!("no public key returned for {}", account_id))
+        })?;
+
+        Ok(Self {
+            endpoint,
+            account_id,
+            public_key,
+            http,
+        })
+    }
+}
+
+#[async_trait]
+impl TxSigner for RemoteSigner {
+    fn account_id(&self) -> &AccountId {
+        &self.account_id
+    }
+
+    fn public_key(&self) -> &PublicKey {
+        &self.public_key
+    }
+
+    async fn sign(&self, data: &[u8]) -> Result<Signature, ClientError> {
+        let response = call(
+            &self.endpoint,
+            &self.http,
+            &SignerRequest::Sign {
+                account_id: &self.account_id,
+                public_key: &self.public_key,
+                data: to_base64(data),
+            },
+        )
+        .await?;
+        let signature = response
+            .signature
+            .ok_or_else(|| ClientError::Signer("no signature returned".to_string()))?;
+
+        // A signature by another key would only be rejected later by the node
+        if !signature.verify(data, &self.public_key) {
+            return Err(ClientError::Signer(format!(
+                "signature does not match {}",
+                self.public_key
+            )));
+        }
+        Ok(signature)
+    }
+}
+
+async fn call(
+    endpoint: &RemoteEndpoint,
+    http: &reqwest::Client,
+    request: &SignerRequest<'_>,
+) -> Result<SignerResponse, ClientError> {
+    let io_error = |err: &dyn std::fmt::Display| {
+        ClientError::Signer(format!("remote signer {:?}: {}", endpoint, err))
+    };
+
+    let body = match endpoint {
+        RemoteEndpoint::Unix(path) => {
+            let exchange = async {
+                let mut line = serde_json::to_vec(request).map_err(|err| io_error(&err))?;
+                line.push(b'\n');
+
+                let mut stream = UnixStream::connect(path)
+                    .await
+                    .map_err(|err| io_error(&err))?;
+                stream
+                    .write_all(&line)
+                    .await
+                    .map_err(|err| io_error(&err))?;
+
+                let mut body = String::new();
+                BufReader::new(stream)
+                    .read_line(&mut body)
+                    .await
+                    .map_err(|err| io_error(&err))?;
+                Ok::<_, ClientError>(body)
+            };
+
+            tokio::time::timeout(SIGNER_TIMEOUT, exchange)
+                .await
+                .map_err(|_| {
+                    io_error(&format!("no answer within {}s", SIGNER_TIMEOUT.as_secs()))
+                })??
+        }
+        RemoteEndpoint::Http(url) => http
+            .post(url)
+            .json(request)
+            .send()
+            .await
+            .map_err(|err| io_error(&err))?
+            .text()
+            .await
+            .map_err(|err| io_error(&err))?,
+    };
+
+    let response: SignerResponse = serde_json::from_str(&body).map_err(|err| io_error(&err))?;
+    match response.error {
+        Some(error) => Err(ClientError::Signer(error)),
+        None => Ok(response),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_crypto::{KeyType, SecretKey};
+    use near_primitives::serialize::from_base64;
+    use serde_json::{json, Value};
+    use tokio::net::UnixListener;
+
+    use crate::test_server::StandIn;
+
+    const DATA: [u8; 32] = [7; 32];
+
+    fn key(seed: &str) -> SecretKey {
+        SecretKey::from_seed(KeyType::ED25519, seed)
+    }
+
+    /// Answers as a signer holding `advertised`, but signs with `signing`.
+    fn answer(request: &Value, advertised: &SecretKey, signing: &SecretKey) -> Value {
+        match request["method"].as_str() {
+            Some("public_key") => json!({ "public_key": advertised.public_key() }),
+            Some("sign") => {
+                let data = from_base64(request["data"].as_str().unwrap()).unwrap();
+                json!({ "signature": signing.sign(&data) })
+            }
+            _ => json!({ "error": "unknown method" }),
+        }
+    }
+
+    async fn http_signer(advertised: SecretKey, signing: SecretKey) -> StandIn {
+        StandIn::start(move |_, request| (200, answer(request, &advertised, &signing))).await
+    }
+
+    /// Signer listening on a fresh Unix socket named after `name`.
+    fn unix_signer(name: &str, advertised: SecretKey, signing: SecretKey) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "earthmind-signer-{}-{}.sock",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let (reader, mut writer) = stream.into_split();
+                let mut line = String::new();
+                BufReader::new(reader).read_line(&mut line).await.unwrap();
+
+                let request: Value = serde_json::from_str(&line).unwrap();
+                let mut response = answer(&request, &advertised, &signing).to_string();
+                response.push('\n');
+                writer.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+
+        path
+    }
+
+    async fn connect(endpoint: RemoteEndpoint) -> RemoteSigner {
+        RemoteSigner::connect(endpoint, "miner.test.near".parse().unwrap())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn http_signer_returns_a_verified_signature() {
+        let server = http_signer(key("miner"), key("miner")).await;
+
+        let signer = connect(RemoteEndpoint::Http(server.url.clone())).await;
+        let signature = signer.sign(&DATA).await.unwrap();
+
+        assert_eq!(signer.public_key(), &key("miner").public_key());
+        assert!(signature.verify(&DATA, &key("miner").public_key()));
+    }
+
+    #[tokio::test]
+    async fn http_signature_by_another_key_is_rejected() {
+        let server = http_signer(key("miner"), key("other")).await;
+
+        let signer = connect(RemoteEndpoint::Http(server.url.clone())).await;
+
+        match signer.sign(&DATA).await {
+            Err(ClientError::Signer(message)) => assert!(message.contains("does not match")),
+            other => panic!("expected a signer error, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn unix_signer_returns_a_verified_signature() {
+        let path = unix_signer("good", key("miner"), key("miner"));
+
+        let signer = connect(RemoteEndpoint::Unix(path.clone())).await;
+        let signature = signer.sign(&DATA).await.unwrap();
+
+        assert_eq!(signer.public_key(), &key("miner").public_key());
+        assert!(signature.verify(&DATA, &key("miner").public_key()));
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[tokio::test]
+    async fn unix_signature_by_another_key_is_rejected() {
+        let path = unix_signer("wrong-key", key("miner"), key("other"));
+
+        let signer = connect(RemoteEndpoint::Unix(path.clone())).await;
+
+        match signer.sign(&DATA).await {
+            Err(ClientError::Signer(message)) => assert!(message.contains("does not match")),
+            other => panic!("expected a signer error, got {:?}", other),
+        }
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,265 @@
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+/// Local HTTP server standing in for an RPC node or relayer in tests.
+///
+/// Every request is answered by `handler` with a status code and JSON body, one request per
+/// connection.
+pub struct StandIn {
+    pub url: String,
+    hits: Arc<AtomicUsize>,
+}
+
+impl StandIn {
+    pub async fn start<F>(handler: F) -> Self
+    where
+        F: Fn(&str, &Value) -> (u16, Value) + Send + Sync + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let hits = Arc::new(AtomicUsize::new(0));
+
+        let handler = Arc::new(handler);
+        let counter = hits.clone();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let handler = handler.clone();
+                let counter = counter.clone();
+                tokio::spawn(async move {
+                    let (path, body) = read_request(&mut stream).await;
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    let (status, response) = handler(&path, &body);
+                    write_response(stream, status, &response).await;
+                });
+            }
+        });
+
+        Self { url, hits }
+    }
+
+    /// Number of requests answered so far.
+    pub fn hits(&self) -> usize {
+        self.hits.load(Ordering::SeqCst)
+    }
+}
+
+/// URL with nothing listening behind it.
+pub async fn unreachable_url() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    format!("http://{}", listener.local_addr().unwrap())
+}
+
+pub fn rpc_result(request: &Value, result: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "id": request["id"], "result": result })
+}
+
+pub fn rpc_error(request: &Value, error: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "id": request["id"], "error": error })
+}
+
+/// `status` result of a synced node.
+pub fn status_result() -> Value {
+    json!({
+        "version": { "version": "1.40.0", "build": "stand-in", "rustc_version": "1.79.0" },
+        "chain_id": "localnet",
+        "protocol_version": 68,
+        "latest_protocol_version": 68,
+        "validators": [],
+        "sync_info": {
+            "latest_block_hash": "11111111111111111111111111111111",
+            "latest_block_height": 100,
+            "latest_state_root": "11111111111111111111111111111111",
+            "latest_block_time": "2024-01-01T00:00:00.000000000Z",
+            "syncing": false
+        },
+        "validator_account_id": null,
+        "validator_public_key": null,
+        "node_public_key": "ed25519:6E8sCci9badyRkXb3JoRpBj5p8C6Tw41ELDZoiihKEtp",
+        "node_key": null,
+        "uptime_sec": 60,
+        "genesis_hash": "11111111111111111111111111111111"
+    })
+}
+
+/// Base58 hash standing in for a block, chunk or transaction hash.
+pub fn hash_of(name: &str) -> String {
+    near_primitives::hash::hash(name.as_bytes()).to_string()
+}
+
+/// `block` result at `height` with the given chunk headers.
+pub fn block_result(height: u64, chunks: Vec<Value>) -> Value {
+    let zero = hash_of("zero");
+    json!({
+        "author": "validator.test.near",
+        "header": {
+            "height": height,
+            "prev_height": height - 1,
+            "epoch_id": zero,
+            "next_epoch_id": zero,
+            "hash": hash_of(&format!("block {}", height)),
+            "prev_hash": hash_of(&format!("block {}", height - 1)),
+            "prev_state_root": zero,
+            "block_body_hash": zero,
+            "chunk_receipts_root": zero,
+            "chunk_headers_root": zero,
+            "chunk_tx_root": zero,
+            "outcome_root": zero,
+            "chunks_included": chunks.len(),
+            "challenges_root": zero,
+            "timestamp": 1_700_000_000_000_000_000u64,
+            "timestamp_nanosec": "1700000000000000000",
+            "random_value": zero,
+            "validator_proposals": [],
+            "chunk_mask": chunks.iter().map(|_| true).collect::<Vec<_>>(),
+            "gas_price": "100000000",
+            "block_ordinal": height,
+            "rent_paid": "0",
+            "validator_reward": "0",
+            "total_supply": "0",
+            "challenges_result": [],
+            "last_final_block": zero,
+            "last_ds_final_block": zero,
+            "next_bp_hash": zero,
+            "block_merkle_root": zero,
+            "epoch_sync_data_hash": null,
+            "approvals": [],
+            "signature": format!("ed25519:{}", "1".repeat(64)),
+            "latest_protocol_version": 68
+        },
+        "chunks": chunks
+    })
+}
+
+/// Header of the chunk `name`, first included at `height_included`.
+pub fn chunk_header(name: &str, height_included: u64) -> Value {
+    let zero = hash_of("zero");
+    json!({
+        "chunk_hash": hash_of(name),
+        "prev_block_hash": zero,
+        "outcome_root": zero,
+        "prev_state_root": zero,
+        "encoded_merkle_root": zero,
+        "encoded_length": 0,
+        "height_created": height_included,
+        "height_included": height_included,
+        "shard_id": 0,
+        "gas_used": 0,
+        "gas_limit": 1_000_000_000_000_000u64,
+        "rent_paid": "0",
+        "validator_reward": "0",
+        "balance_burnt": "0",
+        "outgoing_receipts_root": zero,
+        "tx_root": zero,
+        "validator_proposals": [],
+        "signature": format!("ed25519:{}", "1".repeat(64))
+    })
+}
+
+/// `chunk` result holding `transactions`.
+pub fn chunk_result(header: Value, transactions: Vec<Value>) -> Value {
+    json!({
+        "author": "validator.test.near",
+        "header": header,
+        "transactions": transactions,
+        "receipts": []
+    })
+}
+
+/// Transaction `name` from `signer_id` calling `method_name` on `receiver_id`.
+pub fn transaction_view(
+    name: &str,
+    signer_id: &str,
+    receiver_id: &str,
+    method_name: &str,
+) -> Value {
+    json!({
+        "signer_id": signer_id,
+        "public_key": "ed25519:11111111111111111111111111111111",
+        "nonce": 1,
+        "receiver_id": receiver_id,
+        "actions": [{
+            "FunctionCall": {
+                "method_name": method_name,
+                "args": "e30=",
+                "gas": 100_000_000_000_000u64,
+                "deposit": "0"
+            }
+        }],
+        "priority_fee": 0,
+        "signature": format!("ed25519:{}", "1".repeat(64)),
+        "hash": hash_of(name)
+    })
+}
+
+/// Successful final outcome of `transaction` whose own outcome logged `logs`.
+pub fn outcome_result(transaction: Value, logs: &[&str]) -> Value {
+    let hash = transaction["hash"].clone();
+    json!({
+        "status": { "SuccessValue": "" },
+        "transaction": transaction,
+        "transaction_outcome": {
+            "proof": [],
+            "block_hash": hash_of("zero"),
+            "id": hash,
+            "outcome": {
+                "logs": logs,
+                "receipt_ids": [],
+                "gas_burnt": 1,
+                "tokens_burnt": "1",
+                "executor_id": transaction["receiver_id"],
+                "status": { "SuccessReceiptId": hash_of("zero") },
+                "metadata": { "version": 1, "gas_profile": null }
+            }
+        },
+        "receipts_outcome": []
+    })
+}
+
+async fn read_request(stream: &mut TcpStream) -> (String, Value) {
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 4096];
+
+    let header_end = loop {
+        let read = stream.read(&mut chunk).await.unwrap();
+        assert!(read > 0, "connection closed before the request headers");
+        buffer.extend_from_slice(&chunk[..read]);
+        if let Some(position) = buffer.windows(4).position(|window| window == b"\r\n\r\n") {
+            break position + 4;
+        }
+    };
+
+    let head = String::from_utf8_lossy(&buffer[..header_end]).to_string();
+    let path = head.split_whitespace().nth(1).unwrap_or("/").to_string();
+    let content_length = head
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+        .map(|(_, value)| value.trim().parse::<usize>().unwrap())
+        .unwrap_or(0);
+
+    while buffer.len() < header_end + content_length {
+        let read = stream.read(&mut chunk).await.unwrap();
+        assert!(read > 0, "connection closed before the request body");
+        buffer.extend_from_slice(&chunk[..read]);
+    }
+
+    let body = &buffer[header_end..header_end + content_length];
+    let body = serde_json::from_slice(body).unwrap_or(Value::Null);
+
+    (path, body)
+}
+
+async fn write_response(mut stream: TcpStream, status: u16, body: &Value) {
+    let body = body.to_string();
+    let response = format!(
+        "HTTP/1.1 {} Stand-In\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    let _ = stream.write_all(response.as_bytes()).await;
+    let _ = stream.shutdown().await;
+}
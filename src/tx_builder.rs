@@ -1,77 +1,174 @@
-use near_crypto::InMemorySigner;
+use near_crypto::PublicKey;
 use near_primitives::{
-    action::{Action, FunctionCallAction},
+    account::{AccessKey, AccessKeyPermission, FunctionCallPermission},
+    action::{
+        delegate::{DelegateAction, NonDelegateAction, SignedDelegateAction},
+        Action, AddKeyAction, DeleteKeyAction, FunctionCallAction, StakeAction, TransferAction,
+    },
     hash::CryptoHash,
     transaction::Transaction,
+    types::BlockHeight,
 };
 use near_sdk::AccountId;
 use serde_json::Value;
 
-use crate::{cli::Networks, constants::*};
+use crate::config::Config;
+use crate::constants::DEFAULT_GAS;
+use crate::error::ClientError;
+use crate::signer::TxSigner;
 
+/// Describes one transaction. Each call site starts from `TxBuilder::new`, so nothing carries
+/// over between transactions, and the result is only tied to a key once `build` is given the
+/// signer, nonce and block hash.
+///
+/// Actions run in the order they are added. Key and stake actions only make sense when the
+/// receiver is the signer's own account.
+#[derive(Clone)]
 pub struct TxBuilder {
-    pub signer: InMemorySigner,
-    network: Networks,
-    method_name: String,
-    args: Value,
-    gas: u64,
-    deposit: u128,
+    receiver_id: AccountId,
+    actions: Vec<Action>,
 }
 
 impl TxBuilder {
-    pub fn new(signer: InMemorySigner, network: Networks) -> Self {
+    pub fn new(receiver_id: AccountId) -> Self {
         Self {
-            signer,
-            network,
-            method_name: String::new(),
-            args: Value::Null,
-            gas: 100_000_000_000_000,
-            deposit: 0,
+            receiver_id,
+            actions: Vec::new(),
         }
     }
 
-    pub fn with_method_name(&mut self, method_name: &str) -> &mut Self {
-        self.method_name = method_name.to_string();
+    /// Adds a call with 100 Tgas and no deposit; `with_gas` and `with_deposit` change the
+    /// call added last.
+    pub fn function_call(mut self, method_name: &str, args: Value) -> Self {
+        self.actions
+            .push(Action::FunctionCall(Box::new(FunctionCallAction {
+                method_name: method_name.to_string(),
+                args: serde_json::to_vec(&args).unwrap(),
+                gas: DEFAULT_GAS,
+                deposit: 0,
+            })));
         self
     }
 
-    pub fn with_args(&mut self, args: Value) -> &mut Self {
-        self.args = args;
+    /// Adds a call with the gas and deposit configured for `method_name`.
+    pub fn profiled_call(self, config: &Config, method_name: &str, args: Value) -> Self {
+        let profile = config.method_profile(method_name);
+        self.function_call(method_name, args)
+            .with_gas(profile.gas)
+            .with_deposit(profile.deposit)
+    }
+
+    pub fn with_gas(mut self, gas: u64) -> Self {
+        self.last_function_call().gas = gas;
+        self
+    }
+
+    pub fn with_deposit(mut self, deposit: u128) -> Self {
+        self.last_function_call().deposit = deposit;
         self
     }
 
-    pub fn with_gas(&mut self, gas: u64) -> &mut Self {
-        self.gas = gas;
+    pub fn transfer(mut self, deposit: u128) -> Self {
+        self.actions
+            .push(Action::Transfer(TransferAction { deposit }));
         self
     }
 
-    pub fn with_deposit(&mut self, deposit: u128) -> &mut Self {
-        self.deposit = deposit;
+    pub fn add_full_access_key(self, public_key: PublicKey) -> Self {
+        self.add_key(public_key, AccessKeyPermission::FullAccess)
+    }
+
+    /// Adds a key that may only call `method_names` (all methods if empty) on `receiver_id`,
+    /// spending at most `allowance` on gas.
+    pub fn add_function_call_key(
+        self,
+        public_key: PublicKey,
+        allowance: Option<u128>,
+        receiver_id: &AccountId,
+        method_names: Vec<String>,
+    ) -> Self {
+        self.add_key(
+            public_key,
+            AccessKeyPermission::FunctionCall(FunctionCallPermission {
+                allowance,
+                receiver_id: receiver_id.to_string(),
+                method_names,
+            }),
+        )
+    }
+
+    pub fn delete_key(mut self, public_key: PublicKey) -> Self {
+        self.actions
+            .push(Action::DeleteKey(Box::new(DeleteKeyAction { public_key })));
         self
     }
 
-    pub fn build(&self, nonce: u64, block_hash: CryptoHash) -> (Transaction, CryptoHash) {
-        let transaction = Transaction {
-            signer_id: self.signer.account_id.clone(),
-            public_key: self.signer.public_key.clone(),
+    pub fn stake(mut self, stake: u128, public_key: PublicKey) -> Self {
+        self.actions
+            .push(Action::Stake(Box::new(StakeAction { stake, public_key })));
+        self
+    }
+
+    pub fn build(&self, signer: &dyn TxSigner, nonce: u64, block_hash: CryptoHash) -> Transaction {
+        Transaction {
+            signer_id: signer.account_id().clone(),
+            public_key: signer.public_key().clone(),
             nonce,
-            receiver_id: self.get_receiver_id(),
+            receiver_id: self.receiver_id.clone(),
             block_hash,
-            actions: vec![Action::FunctionCall(Box::new(FunctionCallAction {
-                method_name: self.method_name.clone(),
-                args: serde_json::to_vec(&self.args).unwrap(),
-                gas: self.gas,
-                deposit: self.deposit,
-            }))],
+            actions: self.actions.clone(),
+        }
+    }
+
+    /// Signs the actions as a NEP-366 delegate action, for a relayer to wrap in a transaction
+    /// it pays for. It is valid until `max_block_height`.
+    pub async fn build_delegate(
+        &self,
+        signer: &dyn TxSigner,
+        nonce: u64,
+        max_block_height: BlockHeight,
+    ) -> Result<SignedDelegateAction, ClientError> {
+        let delegate_action = DelegateAction {
+            sender_id: signer.account_id().clone(),
+            receiver_id: self.receiver_id.clone(),
+            actions: self
+                .actions
+                .iter()
+                .cloned()
+                .map(|action| {
+                    NonDelegateAction::try_from(action)
+                        .expect("the builder adds no delegate actions")
+                })
+                .collect(),
+            nonce,
+            max_block_height,
+            public_key: signer.public_key().clone(),
         };
+        let signature = signer
+            .sign(delegate_action.get_nep461_hash().as_bytes())
+            .await?;
+
+        Ok(SignedDelegateAction {
+            delegate_action,
+            signature,
+        })
+    }
 
-        (transaction.clone(), transaction.get_hash_and_size().0)
+    fn add_key(mut self, public_key: PublicKey, permission: AccessKeyPermission) -> Self {
+        self.actions.push(Action::AddKey(Box::new(AddKeyAction {
+            public_key,
+            access_key: AccessKey {
+                nonce: 0,
+                permission,
+            },
+        })));
+        self
     }
 
-    fn get_receiver_id(&self) -> AccountId {
-        match self.network {
-            Networks::Mainnet => EARTHMIND_PROTOCOL_CONTRACT_MAINNET.parse().unwrap(),
-            Networks::Testnet => EARTHMIND_PROTOCOL_CONTRACT_TESTNET.parse().unwrap(),
+    fn last_function_call(&mut self) -> &mut FunctionCallAction {
+        match self.actions.last_mut() {
+            Some(Action::FunctionCall(call)) => call,
+            _ => panic!("with_gas and with_deposit must follow a function_call"),
         }
     }
 }
@@ -1,90 +1,789 @@
-use near_jsonrpc_client::{methods, JsonRpcClient};
-use near_jsonrpc_primitives::types::transactions::{RpcTransactionError, TransactionInfo};
+use near_jsonrpc_client::methods;
+use near_jsonrpc_primitives::types::transactions::{
+    RpcTransactionError, RpcTransactionResponse, TransactionInfo,
+};
+use near_primitives::action::delegate::{DelegateAction, SignedDelegateAction};
+use near_primitives::action::Action;
+use near_primitives::borsh;
+use near_primitives::errors::{ActionError, ActionErrorKind, FunctionCallError, TxExecutionError};
 use near_primitives::hash::CryptoHash;
-use near_primitives::views::TxExecutionStatus;
+use near_primitives::serialize::to_base64;
+use near_primitives::transaction::{SignedTransaction, Transaction};
+use near_primitives::views::{
+    ExecutionStatusView, FinalExecutionOutcomeView, FinalExecutionOutcomeViewEnum,
+    FinalExecutionStatus, TxExecutionStatus,
+};
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
+use tokio::time::sleep;
+
+use crate::constants::{DELEGATE_ACTION_TTL, FINALITY_TRACK_TIMEOUT, MAX_RESIGN_ATTEMPTS};
+use crate::error::ClientError;
+use crate::journal::{self, Journal, JournalRecord, TxContext, TxStatus};
+use crate::key_pool::KeyLease;
+use crate::nonce_manager::NonceManager;
+use crate::offline;
+use crate::processors::extract_logs;
+use crate::relayer::Relayer;
+use crate::rpc_pool::RpcPool;
+use crate::signer::{sign_transaction, TxSigner};
+use crate::tx_builder::TxBuilder;
+
+/// How `TxSender` retries a transaction the RPC could not confirm.
+#[derive(Copy, Clone)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    /// Overall time allowed from the first broadcast
+    pub deadline: Duration,
+}
+
+impl RetryPolicy {
+    /// Exponential backoff with "equal jitter": half the delay is fixed, half is random.
+    fn backoff(&self, attempt: u32) -> Duration {
+        let delay = self
+            .base_delay
+            .saturating_mul(1u32 << attempt.min(16).saturating_sub(1))
+            .min(self.max_delay);
+        let half = delay / 2;
+        half + half.mul_f64(rand::random::<f64>())
+    }
+}
 
 pub struct TxSender {
-    pub client: Arc<JsonRpcClient>,
-    timeout: Duration,
+    pub client: Arc<RpcPool>,
+    retry: RetryPolicy,
+    dry_run: bool,
+    export_dir: Option<PathBuf>,
+    journal: Option<Arc<Journal>>,
+    relayer: Option<Relayer>,
 }
 
 impl TxSender {
-    pub fn new(client: Arc<JsonRpcClient>, timeout: Duration) -> Self {
-        Self { client, timeout }
+    pub fn new(client: Arc<RpcPool>, retry: RetryPolicy) -> Self {
+        Self {
+            client,
+            retry,
+            dry_run: false,
+            export_dir: None,
+            journal: None,
+            relayer: None,
+        }
     }
 
-    pub async fn send_transaction(
-        &self,
-        request: methods::send_tx::RpcSendTransactionRequest,
-    ) -> Result<
-        near_jsonrpc_primitives::types::transactions::RpcTransactionResponse,
-        Box<dyn std::error::Error + Send + Sync>,
-    > {
-        let sent_at = Instant::now();
+    /// Print signed transactions instead of sending them. Queries still go to the RPC.
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
 
-        match self.client.call(request.clone()).await {
-            Ok(response) => {
-                self.log_response_time(sent_at);
-                Ok(response)
+    /// Write transactions unsigned to `export_dir` instead of signing and sending them.
+    pub fn with_export_dir(mut self, export_dir: Option<PathBuf>) -> Self {
+        self.export_dir = export_dir;
+        self
+    }
+
+    /// Record every broadcast transaction and its outcome in `journal`.
+    pub fn with_journal(mut self, journal: Arc<Journal>) -> Self {
+        self.journal = Some(journal);
+        self
+    }
+
+    /// Send calls made through `submit` as NEP-366 meta transactions via the relayer at
+    /// `relayer_url`, which pays their gas.
+    pub fn with_relayer(mut self, relayer_url: Option<&str>) -> Self {
+        self.relayer = relayer_url.map(Relayer::new);
+        self
+    }
+
+    /// Builds the transaction and sends it with `key`: as a delegate action through the relayer
+    /// when one is configured, otherwise signed and paid for by the key's own account.
+    pub async fn submit(
+        &self,
+        builder: TxBuilder,
+        wait_until: TxExecutionStatus,
+        key: &KeyLease<'_>,
+        context: &TxContext,
+    ) -> Result<RpcTransactionResponse, ClientError> {
+        match &self.relayer {
+            // Exporting is per invocation, so it takes precedence over a configured relayer
+            Some(relayer) if self.export_dir.is_none() => {
+                let (nonce, block_height) =
+                    key.nonce_manager().get_nonce_and_block_height().await?;
+                let signed_delegate_action = builder
+                    .build_delegate(key.signer(), nonce, block_height + DELEGATE_ACTION_TTL)
+                    .await?;
+                self.relay(relayer, &signed_delegate_action, context).await
             }
-            Err(err) => {
-                if let Some(RpcTransactionError::TimeoutError) = err.handler_error() {
-                    let tx_hash = request.signed_transaction.get_hash();
-                    let sender_account_id =
-                        request.signed_transaction.transaction.signer_id.clone();
-                    self.wait_for_transaction(tx_hash, sender_account_id, sent_at)
-                        .await
-                } else {
-                    Err(err.into())
-                }
+            _ => {
+                let (nonce, block_hash) = key.nonce_manager().get_nonce_and_tx_hash().await?;
+                let transaction = builder.build(key.signer(), nonce, block_hash);
+                self.sign_and_send(
+                    transaction,
+                    wait_until,
+                    key.signer(),
+                    key.nonce_manager(),
+                    context,
+                )
+                .await
             }
         }
     }
 
-    async fn wait_for_transaction(
+    /// Signs and sends `transaction`, returning once it reaches `wait_until`.
+    ///
+    /// If the node rejects its nonce, the nonce is resynced from the access key; if its block
+    /// hash has expired, a fresh one is taken. Either way the same transaction is re-signed and
+    /// resubmitted, at most `MAX_RESIGN_ATTEMPTS` times.
+    ///
+    /// When `wait_until` is short of `Final`, finality is confirmed in the background and a
+    /// failure that only shows up then is reported. Every signed version is journaled under
+    /// `context`.
+    pub async fn sign_and_send(
         &self,
-        tx_hash: CryptoHash,
-        sender_account_id: near_primitives::types::AccountId,
-        sent_at: Instant,
-    ) -> Result<
-        near_jsonrpc_primitives::types::transactions::RpcTransactionResponse,
-        Box<dyn std::error::Error + Send + Sync>,
-    > {
+        mut transaction: Transaction,
+        wait_until: TxExecutionStatus,
+        signer: &dyn TxSigner,
+        nonce_manager: &NonceManager,
+        context: &TxContext,
+    ) -> Result<RpcTransactionResponse, ClientError> {
+        if let Some(dir) = &self.export_dir {
+            let path = offline::write_unsigned(dir, &transaction)
+                .map_err(|err| ClientError::Export(err.to_string()))?;
+            println!("Unsigned transaction written to {}", path.display());
+            return Ok(RpcTransactionResponse {
+                final_execution_outcome: None,
+                final_execution_status: TxExecutionStatus::None,
+            });
+        }
+
+        if self.dry_run {
+            print_dry_run(
+                &transaction,
+                sign_transaction(signer, transaction.clone()).await?,
+            );
+            return Ok(RpcTransactionResponse {
+                final_execution_outcome: None,
+                final_execution_status: TxExecutionStatus::None,
+            });
+        }
+
+        let mut resigns = 0;
+
         loop {
-            let response = self
-                .client
-                .call(methods::tx::RpcTransactionStatusRequest {
-                    transaction_info: TransactionInfo::TransactionId {
-                        tx_hash,
-                        sender_account_id: sender_account_id.clone(),
-                    },
-                    wait_until: TxExecutionStatus::Final,
-                })
-                .await;
-
-            if sent_at.elapsed() > self.timeout {
-                return Err("Time limit exceeded for the transaction to be recognized".into());
+            let request = methods::send_tx::RpcSendTransactionRequest {
+                signed_transaction: sign_transaction(signer, transaction.clone()).await?,
+                wait_until: wait_until.clone(),
+            };
+            let record =
+                journal_record(&transaction, request.signed_transaction.get_hash(), context);
+            self.journal(&record);
+
+            let result = self.send_transaction(request).await;
+            self.journal_result(record.clone(), &wait_until, &result);
+
+            match result {
+                Err(ClientError::InvalidNonce { tx_nonce, ak_nonce })
+                    if resigns < MAX_RESIGN_ATTEMPTS =>
+                {
+                    resigns += 1;
+                    transaction.nonce = nonce_manager.resync(ak_nonce).await;
+                    println!(
+                        "Nonce {} rejected (access key nonce {}), resubmitting with nonce {}",
+                        tx_nonce, ak_nonce, transaction.nonce
+                    );
+                }
+                Err(ClientError::ExpiredTransaction) if resigns < MAX_RESIGN_ATTEMPTS => {
+                    resigns += 1;
+                    nonce_manager.expire_block_hash().await;
+                    let (nonce, block_hash) = nonce_manager.get_nonce_and_tx_hash().await?;
+                    transaction.nonce = nonce;
+                    transaction.block_hash = block_hash;
+                    println!(
+                        "Block hash expired, resubmitting with block hash {}",
+                        block_hash
+                    );
+                }
+                Ok(response) => return self.finish(&transaction, record, &wait_until, response),
+                Err(err) => return Err(err),
             }
+        }
+    }
+
+    /// Submits a transaction signed elsewhere. Unlike `sign_and_send` it cannot be re-signed,
+    /// so a rejected nonce or an expired block hash is returned as is.
+    pub async fn broadcast(
+        &self,
+        signed_transaction: SignedTransaction,
+        wait_until: TxExecutionStatus,
+    ) -> Result<RpcTransactionResponse, ClientError> {
+        let transaction = signed_transaction.transaction.clone();
+
+        if self.dry_run {
+            print_dry_run(&transaction, signed_transaction);
+            return Ok(RpcTransactionResponse {
+                final_execution_outcome: None,
+                final_execution_status: TxExecutionStatus::None,
+            });
+        }
+        let record = journal_record(
+            &transaction,
+            signed_transaction.get_hash(),
+            &TxContext::default(),
+        );
+        self.journal(&record);
 
-            match response {
+        let result = self
+            .send_transaction(methods::send_tx::RpcSendTransactionRequest {
+                signed_transaction,
+                wait_until: wait_until.clone(),
+            })
+            .await;
+        self.journal_result(record.clone(), &wait_until, &result);
+
+        self.finish(&transaction, record, &wait_until, result?)
+    }
+
+    /// Hands a signed delegate action to the relayer. It is journaled under its NEP-461 hash,
+    /// as the hash of the relayer's transaction is not known up front.
+    async fn relay(
+        &self,
+        relayer: &Relayer,
+        signed_delegate_action: &SignedDelegateAction,
+        context: &TxContext,
+    ) -> Result<RpcTransactionResponse, ClientError> {
+        if self.dry_run {
+            print_dry_run_delegate(signed_delegate_action);
+            return Ok(RpcTransactionResponse {
+                final_execution_outcome: None,
+                final_execution_status: TxExecutionStatus::None,
+            });
+        }
+
+        let record = delegate_record(&signed_delegate_action.delegate_action, context);
+        self.journal(&record);
+
+        let result = relayer.send(signed_delegate_action).await;
+        self.journal_result(record, &TxExecutionStatus::Final, &result);
+
+        let response = result?;
+        if let Some(message) = execution_failure(&response) {
+            return Err(ClientError::ContractPanic(message));
+        }
+        Ok(response)
+    }
+
+    /// Reports the cost or starts finality tracking, and turns a failed execution into an error.
+    fn finish(
+        &self,
+        transaction: &Transaction,
+        record: JournalRecord,
+        wait_until: &TxExecutionStatus,
+        response: RpcTransactionResponse,
+    ) -> Result<RpcTransactionResponse, ClientError> {
+        if *wait_until == TxExecutionStatus::Final {
+            report_cost(transaction, &response);
+        } else {
+            self.track_finality(transaction, record);
+        }
+        if let Some(message) = execution_failure(&response) {
+            return Err(ClientError::ContractPanic(message));
+        }
+        Ok(response)
+    }
+
+    /// Sends an already signed transaction until it reaches the requested status.
+    ///
+    /// After an RPC timeout the transaction is known to the node, so its status is polled
+    /// instead. Transport errors, and a node that does not know the transaction, lead to a
+    /// rebroadcast of the same signed transaction, which the chain executes at most once.
+    pub async fn send_transaction(
+        &self,
+        request: methods::send_tx::RpcSendTransactionRequest,
+    ) -> Result<RpcTransactionResponse, ClientError> {
+        let sent_at = Instant::now();
+        let tx_hash = request.signed_transaction.get_hash();
+        let sender_account_id = request.signed_transaction.transaction.signer_id.clone();
+        let mut accepted = false;
+        let mut attempt = 0;
+
+        loop {
+            let result = if accepted {
+                self.client
+                    .call(methods::tx::RpcTransactionStatusRequest {
+                        transaction_info: TransactionInfo::TransactionId {
+                            tx_hash,
+                            sender_account_id: sender_account_id.clone(),
+                        },
+                        wait_until: request.wait_until.clone(),
+                    })
+                    .await
+            } else {
+                self.client.call(request.clone()).await
+            };
+
+            match result {
                 Ok(response) => {
                     self.log_response_time(sent_at);
                     return Ok(response);
                 }
                 Err(err) => {
-                    if let Some(RpcTransactionError::TimeoutError) = err.handler_error() {
-                        continue;
+                    let timed_out =
+                        matches!(err.handler_error(), Some(RpcTransactionError::TimeoutError));
+                    let unknown = matches!(
+                        err.handler_error(),
+                        Some(RpcTransactionError::UnknownTransaction { .. })
+                    );
+
+                    if timed_out {
+                        println!("Transaction {} pending, polling its status", tx_hash);
+                        accepted = true;
+                    } else if unknown {
+                        println!(
+                            "Transaction {} unknown to the node, rebroadcasting",
+                            tx_hash
+                        );
+                        accepted = false;
+                    } else {
+                        match ClientError::from(err) {
+                            ClientError::RpcTransport(msg) => {
+                                println!("Transaction {} not sent: {}", tx_hash, msg);
+                            }
+                            err => return Err(err),
+                        }
                     }
-                    return Err(err.into());
                 }
             }
+
+            attempt += 1;
+            if attempt >= self.retry.max_attempts || sent_at.elapsed() >= self.retry.deadline {
+                return Err(ClientError::Timeout(format!(
+                    "transaction {} not confirmed after {} attempts in {}s",
+                    tx_hash,
+                    attempt,
+                    sent_at.elapsed().as_secs()
+                )));
+            }
+            sleep(self.retry.backoff(attempt)).await;
         }
     }
 
+    /// Waits in the background for `transaction` to become final, then reports its cost or
+    /// the failure.
+    fn track_finality(&self, transaction: &Transaction, record: JournalRecord) {
+        let client = self.client.clone();
+        let journal = self.journal.clone();
+        let retry = self.retry;
+        let transaction = transaction.clone();
+        let tx_hash = transaction.get_hash_and_size().0;
+
+        tokio::spawn(async move {
+            let started = Instant::now();
+            let mut attempt = 0;
+
+            while started.elapsed() < FINALITY_TRACK_TIMEOUT {
+                let response = client
+                    .call(methods::tx::RpcTransactionStatusRequest {
+                        transaction_info: TransactionInfo::TransactionId {
+                            tx_hash,
+                            sender_account_id: transaction.signer_id.clone(),
+                        },
+                        wait_until: TxExecutionStatus::Final,
+                    })
+                    .await;
+
+                match response {
+                    Ok(response) => {
+                        let failure = execution_failure(&response);
+                        match &failure {
+                            Some(message) => {
+                                eprintln!("FINALITY_FAILURE {}: {}", tx_hash, message)
+                            }
+                            None => println!("Transaction {} is final", tx_hash),
+                        }
+                        report_cost(&transaction, &response);
+                        if let Some(journal) = &journal {
+                            let status = match failure {
+                                Some(_) => TxStatus::Failed,
+                                None => TxStatus::Final,
+                            };
+                            let mut record = updated(record, status);
+                            record.logs = outcome_logs(&response);
+                            record.error = failure;
+                            append(journal, &record);
+                        }
+                        return;
+                    }
+                    Err(err) => {
+                        if let Some(RpcTransactionError::TimeoutError) = err.handler_error() {
+                            continue;
+                        }
+                        match ClientError::from(err) {
+                            ClientError::RpcTransport(_) => {
+                                attempt += 1;
+                                sleep(retry.backoff(attempt)).await;
+                            }
+                            err => {
+                                eprintln!("FINALITY_FAILURE {}: {}", tx_hash, err);
+                                return;
+                            }
+                        }
+                    }
+                }
+            }
+
+            eprintln!(
+                "FINALITY_FAILURE {}: not final after {}s",
+                tx_hash,
+                FINALITY_TRACK_TIMEOUT.as_secs()
+            );
+        });
+    }
+
+    fn journal(&self, record: &JournalRecord) {
+        if let Some(journal) = &self.journal {
+            append(journal, record);
+        }
+    }
+
+    /// Journals what became of the transaction `record` describes.
+    fn journal_result(
+        &self,
+        record: JournalRecord,
+        wait_until: &TxExecutionStatus,
+        result: &Result<RpcTransactionResponse, ClientError>,
+    ) {
+        let Some(journal) = &self.journal else {
+            return;
+        };
+
+        let record = match result {
+            Ok(response) => {
+                let failure = execution_failure(response);
+                let status = match (&failure, wait_until) {
+                    (Some(_), _) => TxStatus::Failed,
+                    (None, _) if response.final_execution_outcome.is_none() => TxStatus::Sent,
+                    (None, TxExecutionStatus::Final) => TxStatus::Final,
+                    (None, _) => TxStatus::Sent,
+                };
+                let mut record = updated(record, status);
+                record.logs = outcome_logs(response);
+                record.error = failure;
+                record
+            }
+            Err(err) => {
+                // The node may still execute a transaction whose confirmation timed out
+                let status = match err {
+                    ClientError::Timeout(_) | ClientError::RpcTransport(_) => TxStatus::Unconfirmed,
+                    _ => TxStatus::Failed,
+                };
+                let mut record = updated(record, status);
+                record.error = Some(err.to_string());
+                record
+            }
+        };
+        append(journal, &record);
+    }
+
     fn log_response_time(&self, sent_at: Instant) {
         let delta = sent_at.elapsed().as_secs();
         println!("Response received after: {}s", delta);
     }
 }
+
+fn print_dry_run(transaction: &Transaction, signed_transaction: SignedTransaction) {
+    println!("DRY_RUN transaction {}", signed_transaction.get_hash());
+    offline::print_details(transaction);
+
+    let bytes = borsh::to_vec(&signed_transaction).expect("a transaction always serializes");
+    println!("  signed {}", to_base64(&bytes));
+}
+
+fn print_dry_run_delegate(signed_delegate_action: &SignedDelegateAction) {
+    let delegate_action = &signed_delegate_action.delegate_action;
+    println!(
+        "DRY_RUN delegate action {}",
+        delegate_action.get_nep461_hash()
+    );
+    println!(
+        "  sender {}, receiver {}, nonce {}, valid until block {}",
+        delegate_action.sender_id,
+        delegate_action.receiver_id,
+        delegate_action.nonce,
+        delegate_action.max_block_height
+    );
+    println!(
+        "  signed {}",
+        serde_json::to_string(signed_delegate_action).expect("a delegate action always serializes")
+    );
+}
+
+/// Prints the gas and tokens burnt by the transaction and all of its receipts.
+fn report_cost(transaction: &Transaction, response: &RpcTransactionResponse) {
+    let Some(outcome) = final_outcome(response) else {
+        return;
+    };
+
+    let (gas_burnt, tokens_burnt) = std::iter::once(&outcome.transaction_outcome)
+        .chain(&outcome.receipts_outcome)
+        .fold((0u64, 0u128), |(gas, tokens), receipt| {
+            (
+                gas + receipt.outcome.gas_burnt,
+                tokens + receipt.outcome.tokens_burnt,
+            )
+        });
+
+    println!(
+        "TX_COST {} [{}]: gas_burnt {}, tokens_burnt {} yoctoNEAR",
+        transaction.get_hash_and_size().0,
+        action_names(&transaction.actions).join(", "),
+        gas_burnt,
+        tokens_burnt
+    );
+}
+
+fn action_names(actions: &[Action]) -> Vec<&str> {
+    actions
+        .iter()
+        .map(|action| match action {
+            Action::FunctionCall(call) => call.method_name.as_str(),
+            Action::Transfer(_) => "transfer",
+            Action::AddKey(_) => "add_key",
+            Action::DeleteKey(_) => "delete_key",
+            Action::Stake(_) => "stake",
+            _ => "other",
+        })
+        .collect()
+}
+
+/// The `pending` record of a transaction about to be sent.
+fn journal_record(
+    transaction: &Transaction,
+    tx_hash: CryptoHash,
+    context: &TxContext,
+) -> JournalRecord {
+    JournalRecord {
+        timestamp: journal::now(),
+        tx_hash: tx_hash.to_string(),
+        signer_id: transaction.signer_id.to_string(),
+        nonce: transaction.nonce,
+        method: action_names(&transaction.actions).join(","),
+        request_id: context.request_id.clone(),
+        role: context.role.clone(),
+        status: TxStatus::Pending,
+        logs: Vec::new(),
+        error: None,
+    }
+}
+
+fn delegate_record(delegate_action: &DelegateAction, context: &TxContext) -> JournalRecord {
+    JournalRecord {
+        timestamp: journal::now(),
+        tx_hash: delegate_action.get_nep461_hash().to_string(),
+        signer_id: delegate_action.sender_id.to_string(),
+        nonce: delegate_action.nonce,
+        method: action_names(&delegate_action.get_actions()).join(","),
+        request_id: context.request_id.clone(),
+        role: context.role.clone(),
+        status: TxStatus::Pending,
+        logs: Vec::new(),
+        error: None,
+    }
+}
+
+/// `record` moved on to `status` now.
+fn updated(mut record: JournalRecord, status: TxStatus) -> JournalRecord {
+    record.timestamp = journal::now();
+    record.status = status;
+    record
+}
+
+/// A journal that cannot be written must not hold up the transaction it describes.
+fn append(journal: &Journal, record: &JournalRecord) {
+    if let Err(err) = journal.append(record) {
+        eprintln!(
+            "JOURNAL_ERROR {} ({}): {}",
+            record.tx_hash,
+            journal.path().display(),
+            err
+        );
+    }
+}
+
+/// Log lines of the transaction and its receipts, for the journal.
+fn outcome_logs(response: &RpcTransactionResponse) -> Vec<String> {
+    extract_logs(response)
+        .into_iter()
+        .map(|outcome_log| outcome_log.log)
+        .collect()
+}
+
+/// The first failure of the transaction or any of its receipts. For a contract panic this is
+/// the panic message.
+fn execution_failure(response: &RpcTransactionResponse) -> Option<String> {
+    let outcome = final_outcome(response)?;
+
+    let error = match &outcome.status {
+        FinalExecutionStatus::Failure(error) => error,
+        _ => std::iter::once(&outcome.transaction_outcome)
+            .chain(&outcome.receipts_outcome)
+            .find_map(|receipt| match &receipt.outcome.status {
+                ExecutionStatusView::Failure(error) => Some(error),
+                _ => None,
+            })?,
+    };
+
+    Some(match error {
+        TxExecutionError::ActionError(ActionError {
+            kind: ActionErrorKind::FunctionCallError(FunctionCallError::ExecutionError(message)),
+            ..
+        }) => message.clone(),
+        other => other.to_string(),
+    })
+}
+
+pub fn final_outcome(response: &RpcTransactionResponse) -> Option<&FinalExecutionOutcomeView> {
+    match response.final_execution_outcome.as_ref()? {
+        FinalExecutionOutcomeViewEnum::FinalExecutionOutcome(outcome) => Some(outcome),
+        FinalExecutionOutcomeViewEnum::FinalExecutionOutcomeWithReceipt(outcome) => {
+            Some(&outcome.final_outcome)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_crypto::{InMemorySigner, KeyType, SecretKey};
+    use near_primitives::serialize::from_base64;
+    use serde_json::{json, Value};
+    use std::sync::Mutex;
+
+    use crate::test_server::{rpc_error, rpc_result, StandIn};
+
+    const AK_NONCE: u64 = 10;
+
+    /// Node whose access key is at `AK_NONCE` and which rejects the first sends with the
+    /// access key nonces in `rejections`. Records the nonce of every transaction sent.
+    async fn node(rejections: Vec<u64>) -> (StandIn, Arc<Mutex<Vec<u64>>>) {
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let nonces = sent.clone();
+
+        let server = StandIn::start(move |_, request| match request["method"].as_str() {
+            Some("query") => (
+                200,
+                rpc_result(
+                    request,
+                    json!({
+                        "nonce": AK_NONCE,
+                        "permission": "FullAccess",
+                        "block_height": 100,
+                        "block_hash": "11111111111111111111111111111111"
+                    }),
+                ),
+            ),
+            Some("send_tx") => {
+                let nonce = sent_nonce(request);
+                let mut nonces = nonces.lock().unwrap();
+                nonces.push(nonce);
+                match rejections.get(nonces.len() - 1) {
+                    Some(ak_nonce) => (200, rpc_error(request, invalid_nonce(nonce, *ak_nonce))),
+                    None => (
+                        200,
+                        rpc_result(request, json!({ "final_execution_status": "INCLUDED" })),
+                    ),
+                }
+            }
+            _ => (
+                200,
+                rpc_error(request, json!({ "code": -32601, "message": "unexpected" })),
+            ),
+        })
+        .await;
+
+        (server, sent)
+    }
+
+    fn sent_nonce(request: &Value) -> u64 {
+        let bytes = from_base64(request["params"]["signed_tx_base64"].as_str().unwrap()).unwrap();
+        let signed_transaction: SignedTransaction = borsh::from_slice(&bytes).unwrap();
+        signed_transaction.transaction.nonce
+    }
+
+    /// The error a node returns for a nonce at or below the access key's.
+    fn invalid_nonce(tx_nonce: u64, ak_nonce: u64) -> Value {
+        json!({
+            "name": "HANDLER_ERROR",
+            "cause": { "name": "INVALID_TRANSACTION", "info": {} },
+            "code": -32000,
+            "message": "Server error",
+            "data": {
+                "TxExecutionError": {
+                    "InvalidTxError": {
+                        "InvalidNonce": { "tx_nonce": tx_nonce, "ak_nonce": ak_nonce }
+                    }
+                }
+            }
+        })
+    }
+
+    async fn send(server: &StandIn) -> Result<RpcTransactionResponse, ClientError> {
+        let client = Arc::new(RpcPool::new(&[server.url.clone()], Duration::from_secs(5)));
+        let signer = Arc::new(InMemorySigner::from_secret_key(
+            "miner.test.near".parse().unwrap(),
+            SecretKey::from_seed(KeyType::ED25519, "miner.test.near"),
+        ));
+        let nonce_manager = NonceManager::new(client.clone(), signer.clone());
+        let sender = TxSender::new(
+            client,
+            RetryPolicy {
+                max_attempts: 1,
+                base_delay: Duration::from_millis(1),
+                max_delay: Duration::from_millis(1),
+                deadline: Duration::from_secs(5),
+            },
+        );
+
+        // Two nonces handed out locally put the counter ahead of the access key
+        nonce_manager.get_nonce_and_tx_hash().await?;
+        let (nonce, block_hash) = nonce_manager.get_nonce_and_tx_hash().await?;
+        let transaction = TxBuilder::new("earthmind.test.near".parse().unwrap())
+            .function_call("commit_by_miner", json!({}))
+            .build(signer.as_ref(), nonce, block_hash);
+
+        sender
+            .sign_and_send(
+                transaction,
+                TxExecutionStatus::Included,
+                signer.as_ref(),
+                &nonce_manager,
+                &TxContext::default(),
+            )
+            .await
+    }
+
+    #[tokio::test]
+    async fn resigns_past_the_higher_of_access_key_and_local_nonce() {
+        // Ahead of the local counter, then a lagging node behind it
+        let (server, sent) = node(vec![20, 3]).await;
+
+        send(&server).await.unwrap();
+
+        assert_eq!(*sent.lock().unwrap(), [AK_NONCE + 2, 21, 22]);
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_max_resign_attempts() {
+        let (server, sent) = node(vec![20; MAX_RESIGN_ATTEMPTS as usize + 5]).await;
+
+        let result = send(&server).await;
+
+        assert!(matches!(
+            result,
+            Err(ClientError::InvalidNonce { ak_nonce: 20, .. })
+        ));
+        assert_eq!(sent.lock().unwrap().len(), MAX_RESIGN_ATTEMPTS as usize + 1);
+    }
+}